@@ -0,0 +1,235 @@
+use crate::util::Boom;
+
+// Rows of bits packed into u64 words. Column 0 is the leftmost character of
+// a line and lives in bit 0 of the row's first word.
+#[derive(Clone, Debug)]
+pub struct BitMatrix {
+    rows: usize,
+    cols: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitMatrix {
+    pub fn zero(rows: usize, cols: usize) -> BitMatrix {
+        let words_per_row = cols.div_ceil(64);
+        BitMatrix {
+            rows: rows,
+            cols: cols,
+            words_per_row: words_per_row,
+            words: vec![0; rows * words_per_row],
+        }
+    }
+
+    // Fills in a row from a line of 0s and 1s. Lines of differing width are
+    // read as numbers: shorter ones are right-aligned and padded with leading
+    // zeros.
+    pub fn set_row(&mut self, row: usize, line: &str) -> Result<(), Boom> {
+        if line.len() > self.cols {
+            return Err(Boom::from_display(format!(
+                "line {} is wider than {} bits",
+                row + 1,
+                self.cols
+            )));
+        }
+        let offset = self.cols - line.len();
+        for (col, b) in line.bytes().enumerate() {
+            match b {
+                b'1' => self.set(row, offset + col),
+                b'0' => {}
+                _ => {
+                    return Err(Boom::from_display(format!(
+                        "line {} has {:?} in column {}",
+                        row + 1,
+                        b as char,
+                        col + 1
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        assert!(row < self.rows, "row out of range");
+        assert!(col < self.cols, "col out of range");
+
+        self.words[row * self.words_per_row + col / 64] >> (col % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: usize, col: usize) {
+        assert!(row < self.rows, "row out of range");
+        assert!(col < self.cols, "col out of range");
+
+        self.words[row * self.words_per_row + col / 64] |= 1 << (col % 64)
+    }
+
+    // number of set bits in every column, visiting only the set bits
+    pub fn column_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.cols];
+        for row in self.words.chunks(self.words_per_row.max(1)) {
+            for (w, word) in row.iter().enumerate() {
+                let mut bits = *word;
+                while bits != 0 {
+                    counts[w * 64 + bits.trailing_zeros() as usize] += 1;
+                    bits &= bits - 1;
+                }
+            }
+        }
+        counts
+    }
+
+    pub fn column_count(&self, col: usize, rows: &[usize]) -> usize {
+        rows.iter().filter(|r| self.get(**r, col)).count()
+    }
+
+    // the row read as a binary number, leftmost column most significant
+    pub fn row_value(&self, row: usize) -> Option<u64> {
+        if self.cols > 64 {
+            return None;
+        }
+        Some((0..self.cols).fold(0, |acc, col| acc << 1 | self.get(row, col) as u64))
+    }
+
+    pub fn row_string(&self, row: usize) -> String {
+        (0..self.cols)
+            .map(|col| if self.get(row, col) { '1' } else { '0' })
+            .collect()
+    }
+
+    // Narrows the rows down one column at a time in the given order, keeping
+    // the bit `criteria` picks. Stops once one row is left; every step taken
+    // is recorded along with the rows it eliminated.
+    pub fn filter_rows(&self, order: BitOrder, criteria: &dyn BitCriteria) -> Filtered {
+        let mut candidates = (0..self.rows).collect::<Vec<_>>();
+        let mut trace = vec![];
+        for col in order.columns(self.cols) {
+            if candidates.len() <= 1 {
+                break;
+            }
+            let ones = self.column_count(col, &candidates);
+            // a bit nobody has would eliminate everyone, so keep the other
+            let mut bit = criteria.keep(ones, candidates.len());
+            if ones == 0 || ones == candidates.len() {
+                bit = ones > 0;
+            }
+            let (kept, eliminated) = candidates
+                .iter()
+                .partition::<Vec<usize>, _>(|r| self.get(**r, col) == bit);
+            trace.push(FilterStep {
+                col: col,
+                ones: ones,
+                zeros: candidates.len() - ones,
+                bit: bit,
+                eliminated: eliminated,
+            });
+            candidates = kept;
+        }
+        Filtered {
+            rows: candidates,
+            trace: trace,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+impl BitOrder {
+    fn columns(&self, cols: usize) -> Box<dyn Iterator<Item = usize>> {
+        match self {
+            BitOrder::MsbFirst => Box::new(0..cols),
+            BitOrder::LsbFirst => Box::new((0..cols).rev()),
+        }
+    }
+}
+
+// Decides which bit survives a column, given how many of the remaining
+// candidates have a 1 there.
+pub trait BitCriteria {
+    fn keep(&self, ones: usize, total: usize) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tie {
+    One,
+    Zero,
+}
+
+impl Tie {
+    fn bit(&self) -> bool {
+        *self == Tie::One
+    }
+}
+
+pub struct MostCommon(pub Tie);
+
+impl BitCriteria for MostCommon {
+    fn keep(&self, ones: usize, total: usize) -> bool {
+        let zeros = total - ones;
+        if ones == zeros {
+            self.0.bit()
+        } else {
+            ones > zeros
+        }
+    }
+}
+
+pub struct LeastCommon(pub Tie);
+
+impl BitCriteria for LeastCommon {
+    fn keep(&self, ones: usize, total: usize) -> bool {
+        let zeros = total - ones;
+        if ones == zeros {
+            self.0.bit()
+        } else {
+            ones < zeros
+        }
+    }
+}
+
+// Keeps 1 when at least `percent` of the candidates have it.
+pub struct Threshold {
+    pub percent: usize,
+}
+
+impl BitCriteria for Threshold {
+    fn keep(&self, ones: usize, total: usize) -> bool {
+        ones * 100 >= total * self.percent
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FilterStep {
+    pub col: usize,
+    pub ones: usize,
+    pub zeros: usize,
+    pub bit: bool,
+    pub eliminated: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Filtered {
+    pub rows: Vec<usize>,
+    pub trace: Vec<FilterStep>,
+}
+
+impl Filtered {
+    pub fn single(&self) -> Option<usize> {
+        match self.rows.as_slice() {
+            [row] => Some(*row),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,281 @@
+// Checks that brackets in a line are balanced and properly nested, for any
+// set of open/close pairs. Characters outside the set are skipped.
+
+use crate::util::Boom;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct Brackets {
+    pairs: Vec<(char, char)>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    // a closer that doesn't match the innermost open bracket
+    Mismatched,
+    // a closer with nothing open
+    UnexpectedClose,
+    // the line ended with brackets still open
+    Unclosed,
+}
+
+// an open bracket waiting for its closer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Open {
+    pub column: usize,
+    pub symbol: char,
+}
+
+// How the scan gets past a bad closer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Recovery {
+    // drop it, as if it weren't there
+    Skip,
+    // take it as the closer that was expected
+    Replace,
+    // it closes the open bracket at this stack index, and everything opened
+    // after that is missing its closer
+    Unwind(usize),
+}
+
+// Columns count chars from 0; `found` is None at the end of the line, and so
+// is `recovery`, which `check` doesn't work out either. At the end of the
+// line `unclosed` is everything still open; for a bad closer it's only the
+// brackets it concerns: the innermost open one, or when it unwinds, the one
+// it closes and everything opened after that.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub kind: ErrorKind,
+    pub column: usize,
+    pub expected: Option<char>,
+    pub found: Option<char>,
+    pub unclosed: Vec<Open>,
+    pub recovery: Option<Recovery>,
+}
+
+impl Brackets {
+    // Every character may appear only once over all the pairs, otherwise
+    // it couldn't be told whether it opens or closes, or which pair it's in.
+    pub fn new(pairs: &[(char, char)]) -> Result<Brackets, Boom> {
+        let mut seen = vec![];
+        for (open, close) in pairs {
+            if open == close {
+                return Err(Boom::from_display(format!(
+                    "bracket pair {}{} opens and closes with the same character",
+                    open, close
+                )));
+            }
+            for c in [open, close] {
+                if seen.contains(c) {
+                    return Err(Boom::from_display(format!(
+                        "{} is used in more than one bracket pair",
+                        c
+                    )));
+                }
+                seen.push(*c);
+            }
+        }
+        Ok(Brackets {
+            pairs: pairs.to_vec(),
+        })
+    }
+
+    pub fn standard() -> Brackets {
+        Brackets {
+            pairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')],
+        }
+    }
+
+    pub fn closer(&self, open: char) -> Option<char> {
+        self.pairs.iter().find(|(o, _)| *o == open).map(|(_, c)| *c)
+    }
+
+    pub fn is_open(&self, c: char) -> bool {
+        self.pairs.iter().any(|(o, _)| *o == c)
+    }
+
+    pub fn is_close(&self, c: char) -> bool {
+        self.pairs.iter().any(|(_, cl)| *cl == c)
+    }
+
+    // the first problem in the line, or None if it's balanced
+    pub fn check(&self, line: &str) -> Option<Diagnostic> {
+        self.scan(line, false).into_iter().next()
+    }
+
+    // Every problem in the line. After a bad closer the scan carries on with
+    // whichever recovery gets furthest before the next problem, so mistakes
+    // made by the recovery itself don't show up as errors.
+    pub fn check_all(&self, line: &str) -> Vec<Diagnostic> {
+        self.scan(line, true)
+    }
+
+    // the innermost open bracket that `c` would close
+    fn closes_at(&self, stack: &OpenStack, c: char) -> Option<usize> {
+        let (open, _) = self.pairs.iter().find(|(_, cl)| *cl == c)?;
+        stack.innermost(*open)
+    }
+
+    fn scan(&self, line: &str, recover: bool) -> Vec<Diagnostic> {
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut diagnostics = vec![];
+        let mut stack = OpenStack::new();
+        for (column, &c) in chars.iter().enumerate() {
+            if self.is_open(c) {
+                stack.push(Open {
+                    column: column,
+                    symbol: c,
+                });
+            } else if self.is_close(c) {
+                let expected = stack.opens.last().and_then(|o| self.closer(o.symbol));
+                if expected == Some(c) {
+                    stack.pop();
+                    continue;
+                }
+
+                let recovery = if recover {
+                    Some(self.pick_recovery(&stack, c, &chars[column + 1..]))
+                } else {
+                    None
+                };
+                diagnostics.push(Diagnostic {
+                    kind: if stack.opens.is_empty() {
+                        ErrorKind::UnexpectedClose
+                    } else {
+                        ErrorKind::Mismatched
+                    },
+                    column: column,
+                    expected: expected,
+                    found: Some(c),
+                    unclosed: match recovery {
+                        Some(Recovery::Unwind(k)) => stack.opens[k..].to_vec(),
+                        _ => stack.opens.last().copied().into_iter().collect(),
+                    },
+                    recovery: recovery,
+                });
+                match recovery {
+                    Some(Recovery::Skip) => {}
+                    Some(Recovery::Replace) => {
+                        stack.pop();
+                    }
+                    Some(Recovery::Unwind(k)) => stack.truncate(k),
+                    None => return diagnostics,
+                }
+            }
+        }
+
+        if !stack.opens.is_empty() {
+            diagnostics.push(Diagnostic {
+                kind: ErrorKind::Unclosed,
+                column: chars.len(),
+                expected: stack.opens.last().and_then(|o| self.closer(o.symbol)),
+                found: None,
+                unclosed: stack.opens,
+                recovery: None,
+            });
+        }
+        diagnostics
+    }
+
+    // Tries every way past the bad closer `c` and keeps the one that gets
+    // furthest into `rest` before the next bad closer, preferring a line
+    // that ends with nothing open; on a tie the earlier of skip, replace and
+    // unwind wins. Each look-ahead stops at its own next problem, and the
+    // winner's is where the scan picks up again, so a line stays linear.
+    fn pick_recovery(&self, stack: &OpenStack, c: char, rest: &[char]) -> Recovery {
+        let depth = stack.opens.len();
+        let mut options = vec![(Recovery::Skip, depth)];
+        if depth > 0 {
+            options.push((Recovery::Replace, depth - 1));
+        }
+        if let Some(k) = self.closes_at(stack, c) {
+            options.push((Recovery::Unwind(k), k));
+        }
+
+        options
+            .into_iter()
+            .min_by_key(|(_, depth)| {
+                let (reached, left_open) = self.look_ahead(&stack.opens[..*depth], rest);
+                (std::cmp::Reverse(reached), left_open)
+            })
+            .unwrap()
+            .0
+    }
+
+    // How far into `rest` the scan gets before a bad closer, on top of
+    // `stack` without copying it, and whether anything is open if it
+    // reaches the end.
+    fn look_ahead(&self, stack: &[Open], rest: &[char]) -> (usize, bool) {
+        let mut depth = stack.len();
+        let mut opened = vec![];
+        for (i, &c) in rest.iter().enumerate() {
+            if self.is_open(c) {
+                opened.push(c);
+            } else if self.is_close(c) {
+                let top = match opened.last() {
+                    Some(o) => Some(*o),
+                    None if depth > 0 => Some(stack[depth - 1].symbol),
+                    None => None,
+                };
+                if top.and_then(|o| self.closer(o)) != Some(c) {
+                    return (i, false);
+                }
+                if opened.pop().is_none() {
+                    depth -= 1;
+                }
+            }
+        }
+        (rest.len(), depth + opened.len() > 0)
+    }
+
+    // the closers that finish off `unclosed`, innermost first
+    pub fn completion(&self, unclosed: &[Open]) -> String {
+        unclosed
+            .iter()
+            .rev()
+            .filter_map(|o| self.closer(o.symbol))
+            .collect()
+    }
+}
+
+// The open brackets so far, plus where each symbol sits in them, so the
+// bracket a closer matches is found without searching the whole stack.
+struct OpenStack {
+    opens: Vec<Open>,
+    by_symbol: HashMap<char, Vec<usize>>,
+}
+
+impl OpenStack {
+    fn new() -> OpenStack {
+        OpenStack {
+            opens: vec![],
+            by_symbol: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, open: Open) {
+        self.by_symbol
+            .entry(open.symbol)
+            .or_default()
+            .push(self.opens.len());
+        self.opens.push(open);
+    }
+
+    fn pop(&mut self) -> Option<Open> {
+        let open = self.opens.pop()?;
+        if let Some(indices) = self.by_symbol.get_mut(&open.symbol) {
+            indices.pop();
+        }
+        Some(open)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        while self.opens.len() > len {
+            self.pop();
+        }
+    }
+
+    fn innermost(&self, symbol: char) -> Option<usize> {
+        self.by_symbol.get(&symbol)?.last().copied()
+    }
+}
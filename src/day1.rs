@@ -1,33 +1,89 @@
-use crate::util::{parse_lines, read_file, BResult};
+use crate::util::{self, BResult, Boom};
+use crate::window::WindowOps;
 
-pub fn run() -> BResult<()> {
-    let input = read_file("input/day1.txt")?;
-    let nums: Vec<i32> = parse_lines(&input)?;
+type Nums = util::FileLines<i64>;
+type Part = fn(&mut Nums) -> Result<(), Boom>;
 
-    part1(&nums);
-    part2(&nums);
+// Every part makes its own pass over the file, so only a window's worth of
+// readings is ever in memory.
+pub fn run() -> BResult<()> {
+    let parts: [Part; 4] = [part1, part2, part3, part4];
+    for part in parts {
+        let mut nums = util::stream_lines::<i64, _>(util::open_file("input/day1.txt")?);
+        part(&mut nums)?;
+    }
 
     Ok(())
 }
 
-fn part1(nums: &[i32]) {
-    let result = nums.array_windows::<2>().filter(less).count();
+fn part1(nums: &mut Nums) -> Result<(), Boom> {
+    let result = nums.by_ref().trend().increases;
+    nums.finish()?;
 
     println!("part 1 result: {}", result);
+    Ok(())
 }
 
-fn part2(nums: &[i32]) {
-    let result = nums
-        .array_windows::<3>()
-        .map(|arr| arr.iter().sum())
-        .collect::<Vec<i32>>() // sums for windows
-        .array_windows::<2>()
-        .filter(less)
-        .count();
+fn part2(nums: &mut Nums) -> Result<(), Boom> {
+    let result = nums.by_ref().rolling(3).map(|w| w.sum).trend().increases;
+    nums.finish()?;
 
     println!("part 2 result: {}", result);
+    Ok(())
 }
 
-fn less(arr: &&[i32; 2]) -> bool {
-    PartialOrd::lt(&arr[0], &arr[1])
+// window size from `--window=N`, defaulting to part 2's three
+fn window_size() -> usize {
+    util::flag_value("window")
+        .and_then(|w| w.parse::<usize>().ok())
+        .filter(|w| *w > 0)
+        .unwrap_or(3)
+}
+
+fn part3(nums: &mut Nums) -> Result<(), Boom> {
+    let size = window_size();
+
+    let mut windows = 0;
+    let mut deepest = None::<crate::window::WindowStats>;
+    let mut widest_spread = 0;
+    let trend = nums
+        .by_ref()
+        .rolling(size)
+        .inspect(|w| {
+            windows += 1;
+            if deepest.is_none_or(|d| w.mean > d.mean) {
+                deepest = Some(*w);
+            }
+            widest_spread = widest_spread.max(w.max - w.min);
+        })
+        .map(|w| w.sum)
+        .trend();
+    nums.finish()?;
+
+    println!(
+        "window {}: {} windows, {} increases, {} decreases, {} unchanged",
+        size, windows, trend.increases, trend.decreases, trend.unchanged
+    );
+    if let Some(d) = deepest {
+        println!(
+            "deepest window starts at {}: mean {:.2}, min {}, max {}",
+            d.start, d.mean, d.min, d.max
+        );
+    }
+    println!("widest spread inside a window: {}", widest_spread);
+    Ok(())
+}
+
+fn part4(nums: &mut Nums) -> Result<(), Boom> {
+    let size = window_size();
+    let steepest = nums
+        .by_ref()
+        .sliding(size)
+        .max_by_key(|w| w[w.len() - 1] - w[0]);
+    nums.finish()?;
+
+    if let Some(w) = steepest {
+        println!("steepest descent over {} readings: {:?}", size, w);
+    }
+    Ok(())
 }
@@ -1,28 +1,41 @@
-use crate::util::{self, BResult};
+use crate::brackets::{Brackets, Diagnostic, ErrorKind, Open, Recovery};
+use crate::util::{self, BResult, Boom};
 
-type Input = [String];
+type Input = util::FileLines<String>;
+type Part = fn(&Brackets, &mut Input) -> Result<(), Boom>;
 
+// Lines are streamed, one pass over the file per part.
 pub fn run() -> BResult<()> {
-    let input_string = util::read_file_maybe_test("input/day10.txt", "input/day10-test.txt")?;
-    let input = util::parse_lines::<String>(&input_string)?;
-
-    part1(&input);
-    part2(&input);
+    let brackets = brackets()?;
+    let parts: [Part; 3] = [part1, part2, part3];
+    for part in parts {
+        let file = util::open_file_maybe_test("input/day10.txt", "input/day10-test.txt")?;
+        let mut lines = util::stream_lines::<String, _>(file);
+        part(&brackets, &mut lines)?;
+    }
 
     Ok(())
 }
 
-fn reflect(c: char) -> char {
-    match c {
-        '(' => ')',
-        '[' => ']',
-        '{' => '}',
-        '<' => '>',
-        ')' => '(',
-        ']' => '[',
-        '}' => '{',
-        '>' => '<',
-        c => c,
+// `--pairs=()[]` swaps in another bracket set; the scores only know the
+// standard closers
+fn brackets() -> Result<Brackets, Boom> {
+    match util::flag_value("pairs") {
+        Some(pairs) => {
+            let chars = pairs.chars().collect::<Vec<_>>();
+            if chars.len() % 2 != 0 {
+                return Err(Boom::from_display(format!(
+                    "--pairs={} has an odd number of characters, expected open/close pairs",
+                    pairs
+                )));
+            }
+            let pairs = chars
+                .chunks_exact(2)
+                .map(|p| (p[0], p[1]))
+                .collect::<Vec<_>>();
+            Brackets::new(&pairs)
+        }
+        None => Ok(Brackets::standard()),
     }
 }
 
@@ -46,73 +59,165 @@ fn auto_score(c: char) -> i32 {
     }
 }
 
-fn get_syntax_score(line: &str) -> i32 {
-    let mut stack = Vec::<char>::new();
-    for c in line.chars() {
-        match c {
-            '(' | '[' | '{' | '<' => stack.push(c),
-            ')' | ']' | '}' | '>' => match stack.pop() {
-                None => return syntax_score(c),
-                Some(t) => {
-                    if t != reflect(c) {
-                        return syntax_score(c);
-                    }
-                }
-            },
-            _ => (),
+// the syntax error score of a corrupted line, 0 for anything else
+fn get_syntax_score(brackets: &Brackets, line: &str) -> i32 {
+    match brackets.check(line) {
+        Some(Diagnostic {
+            kind: ErrorKind::Mismatched | ErrorKind::UnexpectedClose,
+            found: Some(c),
+            ..
+        }) => syntax_score(c),
+        _ => 0,
+    }
+}
+
+// the autocomplete score of an incomplete line, None for anything else
+fn get_auto_score(brackets: &Brackets, line: &str) -> Option<i64> {
+    match brackets.check(line) {
+        Some(d) if d.kind == ErrorKind::Unclosed => {
+            let mut score = 0i64;
+            for c in brackets.completion(&d.unclosed).chars() {
+                score *= 5;
+                score += auto_score(c) as i64;
+            }
+            Some(score)
         }
+        _ => None,
     }
-    0
 }
 
-fn part1(lines: &Input) {
+fn part1(brackets: &Brackets, lines: &mut Input) -> Result<(), Boom> {
     let mut total_score: i32 = 0;
-    for line in lines {
-        total_score += get_syntax_score(line)
+    for line in lines.by_ref() {
+        total_score += get_syntax_score(brackets, &line)
     }
+    lines.finish()?;
 
     println!("part 1 solution: {}", total_score);
+    Ok(())
 }
 
-fn part2(lines: &Input) {
-    fn repair_line(line: &str) -> String {
-        let mut stack = Vec::<char>::new();
-        for c in line.chars() {
-            match c {
-                '(' | '[' | '{' | '<' => stack.push(c),
-                ')' | ']' | '}' | '>' => match stack.pop() {
-                    None => panic!("error"),
-                    Some(t) => {
-                        if t != reflect(c) {
-                            panic!("error")
-                        }
-                    }
-                },
-                _ => (),
-            }
+// Only the scores of incomplete lines are kept, for the median.
+fn part2(brackets: &Brackets, lines: &mut Input) -> Result<(), Boom> {
+    let mut result = lines
+        .by_ref()
+        .filter_map(|s| get_auto_score(brackets, &s))
+        .collect::<Vec<_>>();
+    lines.finish()?;
+
+    if result.is_empty() {
+        println!("part 2 solution: no incomplete lines");
+        return Ok(());
+    }
+    println!("part 2 solution: {}", util::median64(&mut result));
+    Ok(())
+}
+
+// Every problem in every line, not just the first. Prints a tally, and with
+// `--explain` compiler-style messages for each line that isn't clean; those
+// go out as the lines are read, but the tally waits for the whole file.
+fn part3(brackets: &Brackets, lines: &mut Input) -> Result<(), Boom> {
+    let explain = util::has_flag("explain");
+    let (mut clean, mut corrupted, mut incomplete, mut errors) = (0, 0, 0, 0);
+
+    for (i, line) in lines.by_ref().enumerate() {
+        let diagnostics = brackets.check_all(&line);
+        let bad_closers = diagnostics
+            .iter()
+            .filter(|d| d.kind != ErrorKind::Unclosed)
+            .count();
+        errors += bad_closers;
+        if diagnostics.is_empty() {
+            clean += 1;
+            continue;
+        } else if bad_closers > 0 {
+            corrupted += 1;
+        } else {
+            incomplete += 1;
+        }
+
+        if !explain {
+            continue;
         }
-        for c in stack.iter_mut() {
-            *c = reflect(*c)
+        let syntax = get_syntax_score(brackets, &line);
+        let summary = match get_auto_score(brackets, &line) {
+            _ if syntax > 0 => format!("corrupted, syntax score {}", syntax),
+            Some(auto) => format!("incomplete, autocomplete score {}", auto),
+            None => "corrupted".to_string(),
+        };
+        println!("line {}: {}", i + 1, summary);
+        for d in diagnostics.iter() {
+            print!("{}", render_diagnostic(brackets, i + 1, &line, d));
         }
-        stack.reverse();
-        stack.iter().collect()
+        println!();
     }
+    lines.finish()?;
 
-    fn get_auto_score(line: &str) -> i64 {
-        let missing = repair_line(line);
-        let mut score = 0i64;
-        for c in missing.chars() {
-            score *= 5;
-            score += auto_score(c) as i64;
+    println!(
+        "{} clean, {} corrupted with {} bad closers, {} incomplete",
+        clean, corrupted, errors, incomplete
+    );
+    Ok(())
+}
+
+fn quoted(c: Option<char>) -> String {
+    match c {
+        Some(c) => format!("`{}`", c),
+        None => "end of line".to_string(),
+    }
+}
+
+fn render_diagnostic(brackets: &Brackets, number: usize, line: &str, d: &Diagnostic) -> String {
+    let message = match d.kind {
+        ErrorKind::Mismatched => {
+            format!("expected {}, found {}", quoted(d.expected), quoted(d.found))
         }
-        score
+        ErrorKind::UnexpectedClose => format!("unexpected {} with nothing open", quoted(d.found)),
+        ErrorKind::Unclosed => format!("{} brackets left open", d.unclosed.len()),
+    };
+
+    // the open brackets involved get a dash, the offending column a caret
+    let marked: &[Open] = match d.kind {
+        ErrorKind::Mismatched => &d.unclosed[d.unclosed.len() - 1..],
+        ErrorKind::UnexpectedClose => &[],
+        ErrorKind::Unclosed => &d.unclosed,
+    };
+    let mut markers = vec![' '; d.column + 1];
+    for open in marked {
+        markers[open.column] = '-';
     }
+    markers[d.column] = '^';
+    let markers = markers.iter().collect::<String>();
 
-    let mut result = lines
-        .iter()
-        .filter(|s| get_syntax_score(&s) == 0)
-        .map(|s| get_auto_score(&s))
-        .collect::<Vec<_>>();
+    let help = match d.recovery {
+        None => format!(
+            "complete the line with `{}`",
+            brackets.completion(&d.unclosed)
+        ),
+        Some(Recovery::Skip) => "remove it".to_string(),
+        Some(Recovery::Replace) => {
+            format!("replace it with {}", quoted(d.expected))
+        }
+        Some(Recovery::Unwind(_)) => format!(
+            "insert `{}` before it to close the brackets opened since column {}",
+            brackets.completion(&d.unclosed[1..]),
+            d.unclosed[1].column + 1
+        ),
+    };
 
-    println!("part 2 solution: {}", util::median64(&mut result));
+    let gutter = " ".repeat(number.to_string().len());
+    format!(
+        "error: {}\n{}--> line {}, column {}\n{} |\n{} | {}\n{} | {}\n{} = help: {}\n",
+        message,
+        gutter,
+        number,
+        d.column + 1,
+        gutter,
+        number,
+        line,
+        gutter,
+        markers.trim_end(),
+        gutter,
+        help
+    )
 }
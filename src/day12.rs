@@ -1,3 +1,4 @@
+use crate::svg::Svg;
 use crate::util::{self, BResult, Boom};
 use std::collections::HashMap;
 
@@ -41,6 +42,125 @@ impl MapGraph {
             None => vec![],
         }
     }
+
+    fn caves(&self) -> Vec<&str> {
+        let mut caves = self.data.keys().map(|s| s.as_str()).collect::<Vec<_>>();
+        caves.sort();
+        caves
+    }
+
+    // every edge once, in a stable order
+    fn edges(&self) -> Vec<(&str, &str)> {
+        let mut edges = vec![];
+        for from in self.caves() {
+            let mut destinations = self.destinations(from);
+            destinations.sort();
+            for to in destinations {
+                if from < to {
+                    edges.push((from, to));
+                }
+            }
+        }
+        edges
+    }
+
+    fn to_dot(&self, highlight: Option<&Path>) -> String {
+        let mut out = String::new();
+        out.push_str("graph caves {\n");
+        for cave in self.caves() {
+            let style = match cave {
+                "start" | "end" => "shape=doublecircle, style=filled, fillcolor=palegreen",
+                c if is_big_cave(c) => "shape=box, style=filled, fillcolor=lightblue",
+                _ => "shape=ellipse",
+            };
+            out.push_str(&format!("    \"{}\" [{}];\n", cave, style));
+        }
+        for (from, to) in self.edges() {
+            let style = match highlight {
+                Some(p) if p.uses_edge(from, to) => " [color=red, penwidth=2]",
+                _ => "",
+            };
+            out.push_str(&format!("    \"{}\" -- \"{}\"{};\n", from, to, style));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    // caves evenly spaced on a circle, which is plenty for maps this small
+    fn to_svg(&self, highlight: Option<&Path>) -> Svg {
+        const SIZE: f64 = 400.0;
+        const RADIUS: f64 = 150.0;
+
+        let caves = self.caves();
+        let position = |cave: &str| {
+            let i = caves.iter().position(|c| *c == cave).unwrap();
+            let angle = 2.0 * std::f64::consts::PI * i as f64 / caves.len() as f64;
+            (
+                SIZE / 2.0 + RADIUS * angle.cos(),
+                SIZE / 2.0 + RADIUS * angle.sin(),
+            )
+        };
+
+        let mut svg = Svg::new(SIZE, SIZE);
+        svg.apply_args();
+        svg.background("white");
+        for (from, to) in self.edges() {
+            let ((x1, y1), (x2, y2)) = (position(from), position(to));
+            match highlight {
+                Some(p) if p.uses_edge(from, to) => svg.line(x1, y1, x2, y2, "red", 3.0),
+                _ => svg.line(x1, y1, x2, y2, "grey", 1.0),
+            }
+        }
+        for cave in caves.iter() {
+            let (x, y) = position(cave);
+            match *cave {
+                "start" | "end" => svg.circle(x, y, 18.0, "palegreen"),
+                c if is_big_cave(c) => svg.rect(x - 18.0, y - 12.0, 36.0, 24.0, "lightblue"),
+                _ => svg.circle(x, y, 14.0, "lightgrey"),
+            }
+            svg.text(x, y, 12.0, cave);
+        }
+        svg
+    }
+
+    fn to_mermaid(&self, highlight: Option<&Path>) -> String {
+        // mermaid reserves `end`, so node ids get a prefix and caves become labels
+        fn node_id(cave: &str) -> String {
+            format!("cave_{}", cave)
+        }
+
+        let mut out = String::new();
+        out.push_str("graph LR\n");
+        for cave in self.caves() {
+            let node = match cave {
+                "start" | "end" => format!("{}([{}]):::terminal", node_id(cave), cave),
+                c if is_big_cave(c) => format!("{}[{}]:::big", node_id(cave), cave),
+                _ => format!("{}(({})):::small", node_id(cave), cave),
+            };
+            out.push_str(&format!("    {}\n", node));
+        }
+
+        let mut highlighted = vec![];
+        for (i, (from, to)) in self.edges().into_iter().enumerate() {
+            out.push_str(&format!("    {} --- {}\n", node_id(from), node_id(to)));
+            if let Some(p) = highlight {
+                if p.uses_edge(from, to) {
+                    highlighted.push(i.to_string());
+                }
+            }
+        }
+
+        out.push_str("    classDef terminal fill:#98fb98,stroke:#2e8b57,stroke-width:3px\n");
+        out.push_str("    classDef big fill:#add8e6\n");
+        out.push_str("    classDef small fill:#ffffff\n");
+        if highlighted.len() > 0 {
+            out.push_str(&format!(
+                "    linkStyle {} stroke:red,stroke-width:3px\n",
+                highlighted.join(",")
+            ));
+        }
+        out
+    }
 }
 
 struct EdgeRow {
@@ -67,52 +187,31 @@ pub fn run() -> BResult<()> {
 
     let input = Input::from_edges(edges);
 
-    part1(&input);
+    part1(&input)?;
     part2(&input);
+    part3(&input);
 
     Ok(())
 }
 
-fn part1(input: &Input) {
-    println!("{:?}", input);
-
-    fn get_all_paths(g: &MapGraph, from: &str, to: &str) -> Vec<Vec<String>> {
-        fn get_all_paths_impl(
-            g: &MapGraph,
-            cur: &str,
-            to: &str,
-            path: Vec<String>,
-        ) -> Vec<Vec<String>> {
-            let mut result: Vec<Vec<String>> = vec![];
-
-            for ds in g.destinations(cur) {
-                if !is_big_cave(ds) && path.iter().find(|s| *s == ds).is_some() {
-                    continue;
-                }
-
-                let mut new_path = path.clone();
-                new_path.push(ds.to_string());
-                result.extend(get_all_paths_impl(g, ds, to, new_path));
-            }
+fn part1(input: &Input) -> BResult<()> {
+    let paths = get_all_paths(input, "start", "end", &SmallCavesOnce);
+    println!("part 1 solution: {}", paths.len());
 
-            if cur == to {
-                result.push(path);
-            }
-            result
-        }
+    let longest = paths.iter().max_by_key(|p| p.road.len());
+    std::fs::create_dir_all("output")?;
+    std::fs::write("output/day12.dot", input.to_dot(longest))?;
+    std::fs::write("output/day12.mmd", input.to_mermaid(longest))?;
+    input.to_svg(longest).save("output/day12.svg")?;
 
-        get_all_paths_impl(g, from, to, vec![from.to_string()])
-    }
-
-    let paths = get_all_paths(input, "start", "end");
-    println!("part 1 solution: {}", paths.len());
+    Ok(())
 }
 
 #[derive(Clone, Debug)]
 struct Path {
     segments: HashMap<String, i32>,
     road: Vec<String>,
-    hvsct: bool,
+    revisits: i32,
 }
 
 impl Path {
@@ -120,7 +219,7 @@ impl Path {
         Path {
             segments: HashMap::new(),
             road: vec![],
-            hvsct: false,
+            revisits: 0,
         }
     }
 
@@ -142,23 +241,42 @@ impl Path {
             .or_insert(1);
 
         if visit_count > 1 && !is_big_cave(&cave) {
-            self.hvsct = true;
+            self.revisits += 1;
         }
 
         self.road.push(cave);
     }
 
     fn has_visited_small_cave_twice(&self) -> bool {
-        self.hvsct
+        self.revisits > 0
+    }
+
+    fn small_cave_revisits(&self) -> i32 {
+        self.revisits
     }
 
     fn count_visits(&self, cave: &str) -> i32 {
         *self.segments.get(cave).unwrap_or(&0)
     }
 
-    fn is_starting_again(&self, cave: &str) -> bool {
+    fn uses_edge(&self, a: &str, b: &str) -> bool {
+        self.road
+            .windows(2)
+            .any(|w| (w[0] == a && w[1] == b) || (w[0] == b && w[1] == a))
+    }
+
+    // the same walk with the big caves left out
+    fn small_caves_only(&self) -> Path {
+        let mut p = Path::new();
+        for cave in self.road.iter().filter(|c| !is_big_cave(c)) {
+            p.visit(cave);
+        }
+        p
+    }
+
+    fn is_start(&self, cave: &str) -> bool {
         match &self.road.iter().nth(0) {
-            Some(sp) => *sp == cave && self.road.len() > 1,
+            Some(sp) => *sp == cave,
             None => false,
         }
     }
@@ -179,46 +297,201 @@ impl std::fmt::Display for Path {
     }
 }
 
-fn part2(input: &Input) {
-    fn get_all_paths(g: &MapGraph, from: &str, to: &str) -> Vec<Path> {
-        fn get_all_paths_impl(g: &MapGraph, cur: &str, to: &str, path: Path) -> Vec<Path> {
-            if cur == to {
-                // skip testing after reaching the end
-                return vec![path];
-            }
+// Decides which caves a path may step into next. The answer may only depend
+// on the path's small cave visits: `count_paths` treats paths that differ in
+// big caves alone as the same, and checks that in debug builds.
+trait VisitPolicy {
+    fn can_visit(&self, path: &Path, cave: &str) -> bool;
+}
 
-            if path.is_starting_again(cur) {
-                // we're at start again
-                return vec![];
-            }
+// part 1: small caves at most once
+struct SmallCavesOnce;
 
-            let mut result: Vec<Path> = vec![];
-            for ds in g.destinations(cur) {
-                if !is_big_cave(ds) {
-                    let visit_count = path.count_visits(ds);
-                    if visit_count > 1 {
-                        continue;
-                    }
-                    if visit_count > 0 && path.has_visited_small_cave_twice() {
-                        continue;
-                    }
-                }
+impl VisitPolicy for SmallCavesOnce {
+    fn can_visit(&self, path: &Path, cave: &str) -> bool {
+        is_big_cave(cave) || path.count_visits(cave) == 0
+    }
+}
+
+// part 2: a single small cave may be visited twice, start never again
+struct OneSmallCaveTwice;
+
+impl VisitPolicy for OneSmallCaveTwice {
+    fn can_visit(&self, path: &Path, cave: &str) -> bool {
+        if is_big_cave(cave) {
+            return true;
+        }
+        if path.is_start(cave) {
+            return false;
+        }
+        match path.count_visits(cave) {
+            0 => true,
+            1 => !path.has_visited_small_cave_twice(),
+            _ => false,
+        }
+    }
+}
+
+// every small cave may be visited up to k times, start never again
+struct AnyCaveUpTo(i32);
+
+impl VisitPolicy for AnyCaveUpTo {
+    fn can_visit(&self, path: &Path, cave: &str) -> bool {
+        let AnyCaveUpTo(k) = *self;
+        if is_big_cave(cave) {
+            return true;
+        }
+        if path.is_start(cave) {
+            return false;
+        }
+        path.count_visits(cave) < k
+    }
+}
 
-                let mut new_path = path.clone();
-                new_path.visit(ds);
-                result.extend(get_all_paths_impl(g, ds, to, new_path));
+// at most n repeated small cave visits over the whole path, start never again
+struct RevisitsUpTo(i32);
+
+impl VisitPolicy for RevisitsUpTo {
+    fn can_visit(&self, path: &Path, cave: &str) -> bool {
+        let RevisitsUpTo(n) = *self;
+        if is_big_cave(cave) || path.count_visits(cave) == 0 {
+            return true;
+        }
+        !path.is_start(cave) && path.small_cave_revisits() < n
+    }
+}
+
+// wraps another policy, never entering any of the listed caves
+struct Forbidden<'a> {
+    caves: Vec<String>,
+    inner: &'a dyn VisitPolicy,
+}
+
+impl<'a> VisitPolicy for Forbidden<'a> {
+    fn can_visit(&self, path: &Path, cave: &str) -> bool {
+        !self.caves.iter().any(|c| c == cave) && self.inner.can_visit(path, cave)
+    }
+}
+
+fn get_all_paths(g: &MapGraph, from: &str, to: &str, policy: &dyn VisitPolicy) -> Vec<Path> {
+    fn get_all_paths_impl(
+        g: &MapGraph,
+        cur: &str,
+        to: &str,
+        path: Path,
+        policy: &dyn VisitPolicy,
+    ) -> Vec<Path> {
+        if cur == to {
+            // skip testing after reaching the end
+            return vec![path];
+        }
+
+        let mut result: Vec<Path> = vec![];
+        for ds in g.destinations(cur) {
+            if !policy.can_visit(&path, ds) {
+                continue;
             }
 
-            if cur == to {
-                result.push(path);
+            let mut new_path = path.clone();
+            new_path.visit(ds);
+            result.extend(get_all_paths_impl(g, ds, to, new_path, policy));
+        }
+
+        result
+    }
+
+    get_all_paths_impl(g, from, to, Path::from_single(from), policy)
+}
+
+// Same walk as get_all_paths, but only counts. The policies only look at
+// small cave visits, so paths that agree on those and on the current cave
+// have the same number of ways to finish, and each is counted once.
+fn count_paths(g: &MapGraph, from: &str, to: &str, policy: &dyn VisitPolicy) -> usize {
+    type Key = (String, Vec<(String, i32)>);
+
+    fn count_paths_impl(
+        g: &MapGraph,
+        cur: &str,
+        to: &str,
+        path: &Path,
+        policy: &dyn VisitPolicy,
+        memo: &mut HashMap<Key, usize>,
+    ) -> usize {
+        if cur == to {
+            return 1;
+        }
+
+        let mut small_visits = path
+            .segments
+            .iter()
+            .filter(|(cave, _)| !is_big_cave(cave))
+            .map(|(cave, n)| (cave.clone(), *n))
+            .collect::<Vec<_>>();
+        small_visits.sort();
+        let key = (cur.to_string(), small_visits);
+        if let Some(count) = memo.get(&key) {
+            return *count;
+        }
+
+        let mut count = 0;
+        for ds in g.destinations(cur) {
+            let allowed = policy.can_visit(path, ds);
+            debug_assert_eq!(
+                allowed,
+                policy.can_visit(&path.small_caves_only(), ds),
+                "visit policy looked at big caves"
+            );
+            if !allowed {
+                continue;
             }
-            result
+
+            let mut new_path = path.clone();
+            new_path.visit(ds);
+            count += count_paths_impl(g, ds, to, &new_path, policy, memo);
         }
 
-        get_all_paths_impl(g, from, to, Path::from_single(from))
+        memo.insert(key, count);
+        count
     }
 
-    let paths = get_all_paths(input, "start", "end");
+    let mut memo = HashMap::new();
+    count_paths_impl(g, from, to, &Path::from_single(from), policy, &mut memo)
+}
+
+fn part2(input: &Input) {
+    let paths = get_all_paths(input, "start", "end", &OneSmallCaveTwice);
 
     println!("part 2 solution: {}", paths.len());
 }
+
+// Path counts under the other policies. The counts grow very fast with the
+// bound, so this only runs with `--policies`, and `--upto=N` picks the bound.
+fn part3(input: &Input) {
+    if !util::has_flag("policies") {
+        return;
+    }
+    let bound = util::flag_value("upto")
+        .and_then(|n| n.parse::<i32>().ok())
+        .unwrap_or(2);
+
+    let any_cave = count_paths(input, "start", "end", &AnyCaveUpTo(bound));
+    let revisits = count_paths(input, "start", "end", &RevisitsUpTo(bound));
+    println!("any small cave up to {} times: {} paths", bound, any_cave);
+    println!("up to {} small cave revisits: {} paths", bound, revisits);
+
+    let mut small_caves = input
+        .data
+        .keys()
+        .filter(|c| !is_big_cave(c) && *c != "start" && *c != "end")
+        .collect::<Vec<_>>();
+    small_caves.sort();
+
+    for cave in small_caves {
+        let policy = Forbidden {
+            caves: vec![cave.to_string()],
+            inner: &OneSmallCaveTwice,
+        };
+        let paths = count_paths(input, "start", "end", &policy);
+        println!("without {}: {} paths", cave, paths);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::svg::Svg;
 use crate::util::{self, BResult, Boom};
 use gif::{Encoder, Frame};
 use std::str::FromStr;
@@ -132,6 +133,20 @@ impl Table {
         Ok(())
     }
 
+    fn paint_svg(&self, path: &str) -> BResult<()> {
+        let mut svg = Svg::new(self.width() as f64, self.height() as f64);
+        svg.apply_args();
+        svg.background("black");
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if self.get(x, y) {
+                    svg.rect(x as f64, y as f64, 1.0, 1.0, "white");
+                }
+            }
+        }
+        svg.save(path)
+    }
+
     fn print(&self) {
         for r in self.rows.iter().take(self.effective_height) {
             for v in r.values.iter().take(self.effective_width) {
@@ -217,5 +232,6 @@ fn part2(input: &Input) {
 
     table.print();
     table.paint(&mut encoder).unwrap();
+    table.paint_svg("output/day13.svg").unwrap();
     println!("part 2 solution: {}", " LOOK UP ^^^ ");
 }
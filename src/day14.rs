@@ -1,4 +1,6 @@
+use crate::matrix::Matrix;
 use crate::util::{self, BResult, Boom};
+use num_bigint::BigUint;
 use std::collections::HashMap;
 use std::str::FromStr;
 
@@ -40,6 +42,7 @@ pub fn run() -> BResult<()> {
 
     part1(&input);
     part2(&input);
+    part3(&input);
 
     Ok(())
 }
@@ -78,6 +81,143 @@ fn build_rule_map(input: &Input) -> HashMap<String, String> {
     rule_map
 }
 
+// Tracks how many times each adjacent pair occurs instead of the polymer
+// itself, so a step costs O(rules) no matter how long the polymer gets.
+#[derive(Clone, Debug)]
+struct PairCounts {
+    pairs: HashMap<String, BigUint>,
+    last: char,
+}
+
+impl PairCounts {
+    fn from_template(template: &str) -> PairCounts {
+        let chars = template.chars().collect::<Vec<_>>();
+        let mut pairs: HashMap<String, BigUint> = HashMap::new();
+        for w in chars.windows(2) {
+            *pairs.entry(String::from_iter(w)).or_default() += 1u32;
+        }
+
+        PairCounts {
+            pairs: pairs,
+            last: *chars.last().unwrap(),
+        }
+    }
+
+    fn update(&self, rule_map: &HashMap<String, String>) -> PairCounts {
+        let mut pairs: HashMap<String, BigUint> = HashMap::with_capacity(self.pairs.len() * 2);
+        for (pair, count) in self.pairs.iter() {
+            match rule_map.get(pair) {
+                Some(output) => {
+                    *pairs.entry(output[0..2].to_string()).or_default() += count;
+                    *pairs.entry(output[1..3].to_string()).or_default() += count;
+                }
+                None => *pairs.entry(pair.clone()).or_default() += count,
+            }
+        }
+
+        PairCounts {
+            pairs: pairs,
+            last: self.last,
+        }
+    }
+
+    fn run(&self, rule_map: &HashMap<String, String>, steps: usize) -> PairCounts {
+        let mut counts = self.clone();
+        for _ in 0..steps {
+            counts = counts.update(rule_map);
+        }
+        counts
+    }
+
+    // every element starts exactly one pair, except the very last one
+    fn element_counts(&self) -> HashMap<char, BigUint> {
+        let mut elements: HashMap<char, BigUint> = HashMap::new();
+        for (pair, count) in self.pairs.iter() {
+            *elements.entry(pair.chars().nth(0).unwrap()).or_default() += count;
+        }
+        *elements.entry(self.last).or_default() += 1u32;
+        elements
+    }
+
+    fn score(&self) -> BigUint {
+        let elements = self.element_counts();
+        let max = elements.values().max().unwrap();
+        let min = elements.values().min().unwrap();
+        max - min
+    }
+}
+
+// All pairs over the known elements, in a fixed order, so pair counts can be
+// treated as a vector and one insertion step as a matrix.
+struct PairIndex {
+    pairs: Vec<String>,
+}
+
+impl PairIndex {
+    fn from_input(input: &Input) -> PairIndex {
+        let mut elements = input.template.chars().collect::<Vec<_>>();
+        for Rule(i1, i2, o) in input.rules.iter() {
+            elements.extend([i1, i2, o]);
+        }
+        elements.sort();
+        elements.dedup();
+
+        let mut pairs = vec![];
+        for a in elements.iter() {
+            for b in elements.iter() {
+                pairs.push(String::from_iter([a, b]));
+            }
+        }
+        PairIndex { pairs: pairs }
+    }
+
+    fn position(&self, pair: &str) -> usize {
+        self.pairs.iter().position(|p| p == pair).unwrap()
+    }
+
+    fn transition_matrix(&self, rule_map: &HashMap<String, String>) -> Matrix {
+        let mut m = Matrix::zero(self.pairs.len(), self.pairs.len());
+        for (from, pair) in self.pairs.iter().enumerate() {
+            match rule_map.get(pair) {
+                Some(output) => {
+                    m.add_at(self.position(&output[0..2]), from, 1);
+                    m.add_at(self.position(&output[1..3]), from, 1);
+                }
+                None => m.add_at(from, from, 1),
+            }
+        }
+        m
+    }
+
+    fn vector(&self, template: &str) -> Vec<u64> {
+        let chars = template.chars().collect::<Vec<_>>();
+        let mut v = vec![0; self.pairs.len()];
+        for w in chars.windows(2) {
+            v[self.position(&String::from_iter(w))] += 1;
+        }
+        v
+    }
+
+    fn checked_score_after(&self, input: &Input, steps: u64) -> Option<u64> {
+        let rule_map = build_rule_map(input);
+        let counts = self
+            .transition_matrix(&rule_map)
+            .checked_pow(steps)?
+            .checked_apply(&self.vector(&input.template))?;
+
+        let mut elements: HashMap<char, u64> = HashMap::new();
+        for (pair, count) in self.pairs.iter().zip(counts) {
+            *elements.entry(pair.chars().nth(0).unwrap()).or_default() += count;
+        }
+        *elements.entry(input.template.chars().last()?).or_default() += 1;
+        elements.retain(|_, v| *v > 0);
+
+        let max = elements.values().max()?;
+        let min = elements.values().min()?;
+        Some(max - min)
+    }
+}
+
 fn part1(input: &Input) {
     let mut polymer = input.template.to_string();
     let rule_map = build_rule_map(input);
@@ -90,13 +230,29 @@ fn part1(input: &Input) {
 }
 
 fn part2(input: &Input) {
-    let mut polymer = input.template.to_string();
     let rule_map = build_rule_map(input);
+    let counts = PairCounts::from_template(&input.template).run(&rule_map, 40);
 
-    for i in 0..40 {
-        update_polymer(&mut polymer, &rule_map);
-        println!("step {} polymer size {}", i, polymer.len());
+    println!("part 2 solution: {}", counts.score());
+}
+
+fn part3(input: &Input) {
+    let rule_map = build_rule_map(input);
+    let counts = PairCounts::from_template(&input.template);
+
+    for steps in [10, 100, 1000] {
+        let score = counts.run(&rule_map, steps).score().to_string();
+        println!(
+            "score after {} steps has {} digits: {}",
+            steps,
+            score.len(),
+            score
+        );
     }
 
-    println!("part 2 solution: {}", calculate_score(&polymer));
+    let index = PairIndex::from_input(input);
+    println!(
+        "matrix score after 40 steps: {:?}",
+        index.checked_score_after(input, 40)
+    );
 }
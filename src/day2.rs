@@ -1,66 +1,398 @@
-use crate::util::{parse_lines, read_file, BResult, Boom};
-use std::str::FromStr;
+use crate::util::{self, BResult, Boom};
+use image::{ImageBuffer, Rgb};
+use std::iter::Peekable;
 
-struct Instruction {
-    pub command: String,
-    pub value: i32,
+#[derive(Clone, Debug)]
+enum Command {
+    Forward(i64),
+    Back(i64),
+    Down(i64),
+    Up(i64),
+    Turn,
+    Repeat(i64, Vec<Command>),
 }
 
-impl FromStr for Instruction {
-    type Err = Boom;
+struct Token {
+    line: usize,
+    text: String,
+}
 
-    fn from_str(s: &str) -> Result<Instruction, Boom> {
-        let spl = s.split_ascii_whitespace().collect::<Vec<_>>();
-        let val = spl[1].parse::<i32>().map_err(Boom::from_display)?;
-        Ok(Instruction {
-            command: spl[0].to_string(),
-            value: val,
-        })
+fn tokenize<'a>(lines: impl Iterator<Item = String> + 'a) -> impl Iterator<Item = Token> + 'a {
+    lines.enumerate().flat_map(|(i, line)| {
+        // braces don't need surrounding whitespace
+        line.replace('{', " { ")
+            .replace('}', " } ")
+            .split_ascii_whitespace()
+            .map(|word| Token {
+                line: i + 1,
+                text: word.to_string(),
+            })
+            .collect::<Vec<_>>()
+    })
+}
+
+fn line_error<T: std::fmt::Display>(line: usize, message: T) -> Boom {
+    Boom::from_display(format!("line {}: {}", line, message))
+}
+
+fn parse_program(tokens: impl Iterator<Item = Token>) -> Result<Vec<Command>, Boom> {
+    parse_block(&mut tokens.peekable(), None)
+}
+
+fn parse_block<I: Iterator<Item = Token>>(
+    tokens: &mut Peekable<I>,
+    opened: Option<usize>,
+) -> Result<Vec<Command>, Boom> {
+    let mut commands = vec![];
+    while let Some(command) = parse_command(tokens, opened)? {
+        commands.push(command);
     }
+    Ok(commands)
 }
 
-pub fn run() -> BResult<()> {
-    let input = read_file("input/day2.txt")?;
-    let instructions: Vec<Instruction> = parse_lines(&input)?;
+// The next command, or None at the end of input or at the `}` matching the
+// `repeat` on line `opened`. Only a repeat body is read ahead, so a flat
+// program can be run as it's parsed.
+fn parse_command<I: Iterator<Item = Token>>(
+    tokens: &mut Peekable<I>,
+    opened: Option<usize>,
+) -> Result<Option<Command>, Boom> {
+    let token = match tokens.next() {
+        Some(token) => token,
+        None => {
+            return match opened {
+                Some(line) => Err(line_error(line, "repeat block is never closed")),
+                None => Ok(None),
+            }
+        }
+    };
 
-    part1(&instructions);
-    part2(&instructions);
+    let command = match token.text.as_str() {
+        "}" => {
+            return match opened {
+                Some(_) => Ok(None),
+                None => Err(line_error(token.line, "unexpected }")),
+            }
+        }
+        "forward" => Command::Forward(argument(tokens, &token)?),
+        "back" => Command::Back(argument(tokens, &token)?),
+        "down" => Command::Down(argument(tokens, &token)?),
+        "up" => Command::Up(argument(tokens, &token)?),
+        "turn" => Command::Turn,
+        "repeat" => {
+            let times = argument(tokens, &token)?;
+            if times < 0 {
+                return Err(line_error(token.line, "repeat count can't be negative"));
+            }
+            match tokens.next() {
+                Some(brace) if brace.text == "{" => {}
+                _ => return Err(line_error(token.line, "repeat needs a { after its count")),
+            }
+            Command::Repeat(times, parse_block(tokens, Some(token.line))?)
+        }
+        other => return Err(line_error(token.line, format!("unknown command {}", other))),
+    };
+    Ok(Some(command))
+}
 
-    Ok(())
+// the number following a command, which has to be on the same line
+fn argument<I: Iterator<Item = Token>>(
+    tokens: &mut Peekable<I>,
+    command: &Token,
+) -> Result<i64, Boom> {
+    match tokens.next_if(|t| t.line == command.line) {
+        Some(t) => t
+            .text
+            .parse::<i64>()
+            .map_err(|e| line_error(t.line, format!("bad number {}: {}", t.text, e))),
+        None => Err(line_error(
+            command.line,
+            format!("{} needs a number", command.text),
+        )),
+    }
 }
 
-fn part1(instructions: &[Instruction]) {
-    let mut depth = 0;
-    let mut horizontal = 0;
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct State {
+    horizontal: i64,
+    depth: i64,
+    aim: i64,
+}
+
+// How a submarine reacts to the basic moves; `up` and `back` are just
+// `down` and `forward` the other way.
+trait Navigation {
+    fn forward(&mut self, amount: i64);
+    fn down(&mut self, amount: i64);
+    fn turn(&mut self);
+    fn state(&self) -> State;
+}
 
-    for instruction in instructions {
-        match instruction.command.as_str() {
-            "forward" => horizontal += instruction.value,
-            "down" => depth += instruction.value,
-            "up" => depth -= instruction.value,
-            _ => println!("{}", "unknown command"),
+// part 1: down and up change depth directly
+struct PlainModel {
+    state: State,
+    heading: i64,
+}
+
+impl PlainModel {
+    fn new() -> PlainModel {
+        PlainModel {
+            state: State::default(),
+            heading: 1,
         }
     }
+}
 
-    println!("part 1 result: {}", depth * horizontal);
+impl Navigation for PlainModel {
+    fn forward(&mut self, amount: i64) {
+        self.state.horizontal += self.heading * amount;
+    }
+
+    fn down(&mut self, amount: i64) {
+        self.state.depth += amount;
+    }
+
+    fn turn(&mut self) {
+        self.heading = -self.heading;
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
+}
+
+// part 2: down and up change the aim, and moving follows it
+struct AimModel {
+    state: State,
+    heading: i64,
+}
+
+impl AimModel {
+    fn new() -> AimModel {
+        AimModel {
+            state: State::default(),
+            heading: 1,
+        }
+    }
 }
 
-fn part2(instructions: &[Instruction]) {
-    let mut aim = 0;
-    let mut depth = 0;
-    let mut horizontal = 0;
+impl Navigation for AimModel {
+    fn forward(&mut self, amount: i64) {
+        self.state.horizontal += self.heading * amount;
+        self.state.depth += self.state.aim * amount;
+    }
+
+    fn down(&mut self, amount: i64) {
+        self.state.aim += amount;
+    }
+
+    fn turn(&mut self) {
+        self.heading = -self.heading;
+    }
+
+    fn state(&self) -> State {
+        self.state
+    }
+}
 
-    for instruction in instructions {
-        match instruction.command.as_str() {
-            "forward" => {
-                horizontal += instruction.value;
-                depth += aim * instruction.value;
+// Runs the program, handing the state after every basic move to `record`.
+fn execute(commands: &[Command], sub: &mut dyn Navigation, record: &mut dyn FnMut(State)) {
+    for command in commands {
+        match command {
+            Command::Forward(n) => sub.forward(*n),
+            Command::Back(n) => sub.forward(-n),
+            Command::Down(n) => sub.down(*n),
+            Command::Up(n) => sub.down(-n),
+            Command::Turn => sub.turn(),
+            Command::Repeat(times, body) => {
+                for _ in 0..*times {
+                    execute(body, sub, record);
+                }
+                continue;
             }
-            "down" => aim += instruction.value,
-            "up" => aim -= instruction.value,
-            _ => println!("{}", "unknown command"),
         }
+        record(sub.state());
+    }
+}
+
+// every state the submarine passes through, starting with the initial one
+fn trajectory(commands: &[Command], sub: &mut dyn Navigation) -> Vec<State> {
+    let mut track = vec![sub.state()];
+    execute(commands, sub, &mut |state| track.push(state));
+    track
+}
+
+// Runs commands as they are parsed, without keeping the program around.
+fn follow(tokens: impl Iterator<Item = Token>, sub: &mut dyn Navigation) -> Result<State, Boom> {
+    let mut tokens = tokens.peekable();
+    while let Some(command) = parse_command(&mut tokens, None)? {
+        execute(&[command], sub, &mut |_| {});
+    }
+    Ok(sub.state())
+}
+
+fn track_to_csv(track: &[State]) -> String {
+    let mut out = "step,horizontal,depth,aim\n".to_string();
+    for (step, state) in track.iter().enumerate() {
+        out.push_str(&format!(
+            "{},{},{},{}\n",
+            step, state.horizontal, state.depth, state.aim
+        ));
+    }
+    out
+}
+
+fn track_to_json(track: &[State]) -> String {
+    let rows = track
+        .iter()
+        .map(|s| {
+            format!(
+                r#"  {{"horizontal": {}, "depth": {}, "aim": {}}}"#,
+                s.horizontal, s.depth, s.aim
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
+
+// Depth against horizontal position, one panel per track stacked top to
+// bottom. Panels share the horizontal scale but each fits its own depths.
+fn plot_tracks(tracks: &[&[State]], path: &str) -> BResult<()> {
+    let (width, panel_height, margin) = (800u32, 300u32, 10i64);
+    let colors = [Rgb([40, 90, 200]), Rgb([200, 60, 40])];
+
+    let states = tracks.iter().flat_map(|t| t.iter());
+    let min_x = states.clone().map(|s| s.horizontal).min().unwrap_or(0);
+    let max_x = states.map(|s| s.horizontal).max().unwrap_or(0);
+
+    let mut img = ImageBuffer::from_pixel(
+        width,
+        panel_height * tracks.len() as u32,
+        Rgb([255u8, 255, 255]),
+    );
+    for (i, track) in tracks.iter().enumerate() {
+        let top = (i as u32 * panel_height) as i64;
+        let min_d = track.iter().map(|s| s.depth).min().unwrap_or(0);
+        let max_d = track.iter().map(|s| s.depth).max().unwrap_or(0);
+        let scale = |value: i64, lo: i64, hi: i64, size: i64| {
+            margin as f64
+                + (value - lo) as f64 / (hi - lo).max(1) as f64 * (size - 2 * margin) as f64
+        };
+        let to_pixel = |s: &State| {
+            (
+                scale(s.horizontal, min_x, max_x, width as i64),
+                top as f64 + scale(s.depth, min_d, max_d, panel_height as i64),
+            )
+        };
+
+        for x in 0..width {
+            img.put_pixel(x, top as u32, Rgb([180, 180, 180]));
+        }
+        for pair in track.windows(2) {
+            let (x1, y1) = to_pixel(&pair[0]);
+            let (x2, y2) = to_pixel(&pair[1]);
+            let steps = (x2 - x1).abs().max((y2 - y1).abs()).ceil().max(1.0) as usize;
+            for k in 0..=steps {
+                let t = k as f64 / steps as f64;
+                let (x, y) = (x1 + (x2 - x1) * t, y1 + (y2 - y1) * t);
+                img.put_pixel(x as u32, y as u32, colors[i % colors.len()]);
+            }
+        }
+    }
+
+    Ok(img.save(path)?)
+}
+
+type Input = util::FileLines<String>;
+type Part = fn(&mut Input) -> Result<(), Boom>;
+
+fn open_input() -> BResult<Input> {
+    let file = util::open_file_maybe_test("input/day2.txt", "input/day2-test.txt")?;
+    Ok(util::stream_lines::<String, _>(file))
+}
+
+pub fn run() -> BResult<()> {
+    // parts 1 and 2 run the commands as they're read, one pass each
+    let parts: [Part; 2] = [part1, part2];
+    for part in parts {
+        part(&mut open_input()?)?;
+    }
+    part3();
+
+    // recording whole trajectories takes memory in proportion to the input
+    if util::has_flag("stream") {
+        return Ok(());
+    }
+
+    // a read error cuts the program short, so report that before any parse
+    // error it causes
+    let mut lines = open_input()?;
+    let program = parse_program(tokenize(lines.by_ref()));
+    lines.finish()?;
+    let program = program?;
+    part4(&program)?;
+
+    Ok(())
+}
+
+fn part1(lines: &mut Input) -> Result<(), Boom> {
+    let state = follow(tokenize(lines.by_ref()), &mut PlainModel::new());
+    lines.finish()?;
+    let state = state?;
+
+    println!("part 1 result: {}", state.depth * state.horizontal);
+    Ok(())
+}
+
+fn part2(lines: &mut Input) -> Result<(), Boom> {
+    let state = follow(tokenize(lines.by_ref()), &mut AimModel::new());
+    lines.finish()?;
+    let state = state?;
+
+    println!("part 2 result: {}", state.depth * state.horizontal);
+    Ok(())
+}
+
+// the extended instruction set, on a made up program
+fn part3() {
+    let text =
+        "down 2\nrepeat 3 {\n  forward 4\n  up 1\n}\nturn\nback 2\nrepeat 2 { down 1 forward 1 }";
+    let program = match parse_program(tokenize(text.lines().map(String::from))) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("demo program: {}", e);
+            return;
+        }
+    };
+
+    let plain = trajectory(&program, &mut PlainModel::new());
+    let aim = trajectory(&program, &mut AimModel::new());
+    println!("demo program, plain model: {:?}", plain.last().unwrap());
+    println!("demo program, aim model: {:?}", aim.last().unwrap());
+
+    for broken in ["forward\n5", "down 1\nsideways 3", "repeat 2 {\nup 1"] {
+        if let Err(e) = parse_program(tokenize(broken.lines().map(String::from))) {
+            println!("{:?} -> {}", broken, e);
+        }
+    }
+}
+
+// where the two models part ways, plus the tracks for a closer look
+fn part4(program: &[Command]) -> BResult<()> {
+    let plain = &trajectory(program, &mut PlainModel::new());
+    let aim = &trajectory(program, &mut AimModel::new());
+    match plain.iter().zip(aim).position(|(p, a)| p.depth != a.depth) {
+        Some(step) => println!(
+            "models first disagree on depth after step {}: {} vs {}",
+            step, plain[step].depth, aim[step].depth
+        ),
+        None => println!("models agree on depth all the way"),
     }
 
-    println!("part 2 result: {}", depth * horizontal);
+    std::fs::create_dir_all("output")?;
+    std::fs::write("output/day2-plain.csv", track_to_csv(plain))?;
+    std::fs::write("output/day2-aim.csv", track_to_csv(aim))?;
+    std::fs::write("output/day2-plain.json", track_to_json(plain))?;
+    std::fs::write("output/day2-aim.json", track_to_json(aim))?;
+    plot_tracks(&[plain, aim], "output/day2.png")
 }
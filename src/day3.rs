@@ -1,31 +1,94 @@
-use crate::util::{parse_lines, read_file, BResult};
+use crate::bitmatrix::{
+    BitCriteria, BitMatrix, BitOrder, Filtered, LeastCommon, MostCommon, Threshold, Tie,
+};
+use crate::util::{self, BResult, Boom};
+
+type Input = util::FileLines<String>;
+
+fn open_input() -> BResult<Input> {
+    Ok(util::stream_lines::<String, _>(util::open_file(
+        "input/day3.txt",
+    )?))
+}
 
 pub fn run() -> BResult<()> {
-    let input = read_file("input/day3.txt")?;
-    let values: Vec<String> = parse_lines(&input)?;
+    part1(&mut open_input()?)?;
+
+    // the ratings narrow down the whole report, so they need it in memory
+    if util::has_flag("stream") {
+        return Ok(());
+    }
+
+    let (report, short_lines) = read_report()?;
+    if short_lines > 0 {
+        println!(
+            "warning: {} lines are shorter than {} bits, padding them with leading zeros",
+            short_lines,
+            report.cols()
+        );
+    }
 
-    part1(&values);
-    part2(&values);
+    part2(&report);
+    part3(&report);
 
     Ok(())
 }
 
-fn part1(values: &[String]) {
-    let total_values = values.len();
-    let width = values[0].len();
-    let threshold = total_values / 2;
+// Packs the report without holding its lines: one pass over the file finds
+// its shape, a second fills in the bits. Also says how many lines were
+// shorter than the widest one.
+fn read_report() -> BResult<(BitMatrix, usize)> {
+    let mut lines = open_input()?;
+    let (mut rows, mut cols) = (0, 0);
+    for line in lines.by_ref() {
+        rows += 1;
+        cols = line.len().max(cols);
+    }
+    lines.finish()?;
+
+    let mut report = BitMatrix::zero(rows, cols);
+    let mut short_lines = 0;
+    let mut lines = open_input()?;
+    for (row, line) in lines.by_ref().take(rows).enumerate() {
+        report.set_row(row, &line)?;
+        if line.len() < cols {
+            short_lines += 1;
+        }
+    }
+    lines.finish()?;
 
-    let mut counts: Vec<usize> = vec![0; width];
-    for value in values {
-        for i in 0..width {
-            let bit: usize = match value.chars().nth(i).unwrap() {
-                '1' => 1,
-                _ => 0,
-            };
-            counts[i] += bit;
+    Ok((report, short_lines))
+}
+
+// Streams the report, keeping only a count per column. Counts are indexed
+// from the right while reading, so shorter lines line up the same way
+// `BitMatrix` pads them.
+fn part1(lines: &mut Input) -> Result<(), Boom> {
+    let mut counts: Vec<usize> = vec![];
+    let mut total_values = 0;
+    for line in lines.by_ref() {
+        total_values += 1;
+        if line.len() > counts.len() {
+            counts.resize(line.len(), 0);
+        }
+        for (i, b) in line.bytes().rev().enumerate() {
+            match b {
+                b'1' => counts[i] += 1,
+                b'0' => {}
+                _ => {
+                    return Err(Boom::from_display(format!(
+                        "line {} has {:?} in it",
+                        total_values, b as char
+                    )))
+                }
+            }
         }
     }
+    lines.finish()?;
+    counts.reverse();
 
+    let width = counts.len();
+    let threshold = total_values / 2;
     let result_number = counts
         .iter()
         .map(|x| if x > &threshold { "1" } else { "0" })
@@ -33,21 +96,33 @@ fn part1(values: &[String]) {
     println!("counts: {:?}", counts);
     println!("number: {}", result_number);
 
+    if width > 63 {
+        println!("part 1 result: too wide for a single number");
+        return Ok(());
+    }
+
     let gamma = parse_binary(&result_number);
-    let epsilon = !gamma & ((2usize).pow((width) as u32) - 1);
+    let epsilon = !gamma & ((1 << width) - 1);
 
     println!("gamma: {} epsilon: {}", gamma, epsilon);
-    println!("part 1 result: {}", gamma * epsilon);
+    println!("part 1 result: {}", gamma as u128 * epsilon as u128);
+    Ok(())
 }
 
-fn part2(values: &[String]) {
-    let total_values = values.len();
+fn part2(report: &BitMatrix) {
+    let oxygen = report.filter_rows(BitOrder::MsbFirst, &MostCommon(Tie::One));
+    let co2 = report.filter_rows(BitOrder::MsbFirst, &LeastCommon(Tie::Zero));
 
-    let oxygen_generator_id = find_index(values, (0..total_values).collect(), 0, true).unwrap();
-    let co2_scrubber_id = find_index(values, (0..total_values).collect(), 0, false).unwrap();
+    let (oxygen_generator_id, co2_scrubber_id) = match (oxygen.single(), co2.single()) {
+        (Some(o), Some(c)) => (o, c),
+        _ => {
+            println!("part 2 result: ratings are not unique");
+            return;
+        }
+    };
 
-    let oxygen_generator = &values[oxygen_generator_id];
-    let co2_scrubber = &values[co2_scrubber_id];
+    let oxygen_generator = report.row_string(oxygen_generator_id);
+    let co2_scrubber = report.row_string(co2_scrubber_id);
     println!(
         "oxygen generator id: {}, value: {}",
         oxygen_generator_id, oxygen_generator
@@ -57,47 +132,57 @@ fn part2(values: &[String]) {
         co2_scrubber_id, co2_scrubber
     );
 
-    println!(
-        "part 2 result: {}",
-        parse_binary(oxygen_generator) * parse_binary(co2_scrubber)
-    );
+    match (
+        report.row_value(oxygen_generator_id),
+        report.row_value(co2_scrubber_id),
+    ) {
+        (Some(o), Some(c)) => println!("part 2 result: {}", o as u128 * c as u128),
+        _ => println!("part 2 result: too wide for a single number"),
+    }
 }
 
-fn find_index(
-    values: &[String],
-    indices: Vec<usize>,
-    depth: usize,
-    is_most_common: bool,
-) -> Option<usize> {
-    let total_values = indices.len();
-    if total_values < 1 {
-        return None;
-    };
-    if total_values == 1 {
-        return Some(indices[0]);
+fn part3(report: &BitMatrix) {
+    let rules: Vec<(&str, BitOrder, Box<dyn BitCriteria>)> = vec![
+        ("oxygen", BitOrder::MsbFirst, Box::new(MostCommon(Tie::One))),
+        ("co2", BitOrder::MsbFirst, Box::new(LeastCommon(Tie::Zero))),
+        (
+            "oxygen from lsb",
+            BitOrder::LsbFirst,
+            Box::new(MostCommon(Tie::One)),
+        ),
+        (
+            "co2 from lsb",
+            BitOrder::LsbFirst,
+            Box::new(LeastCommon(Tie::Zero)),
+        ),
+        (
+            "at least 60% ones",
+            BitOrder::MsbFirst,
+            Box::new(Threshold { percent: 60 }),
+        ),
+    ];
+
+    for (name, order, criteria) in rules.iter() {
+        let filtered = report.filter_rows(*order, criteria.as_ref());
+        match filtered.single() {
+            Some(row) => println!("{}: row {}, value {}", name, row, report.row_string(row)),
+            None => println!("{}: {} rows left", name, filtered.rows.len()),
+        }
+        print_trace(&filtered);
     }
+}
 
-    let width = values[0].len();
-    if depth >= width {
-        return None;
-    };
-
-    let zeros = indices
-        .iter()
-        .filter(|i| values[**i].chars().nth(depth) == Some('0'))
-        .map(|x| *x)
-        .collect::<Vec<_>>();
-    let ones = indices
-        .iter()
-        .filter(|i| values[**i].chars().nth(depth) == Some('1'))
-        .map(|x| *x)
-        .collect::<Vec<_>>();
-
-    match (ones.len() >= zeros.len(), is_most_common) {
-        (true, true) => find_index(values, ones, depth + 1, is_most_common),
-        (true, false) => find_index(values, zeros, depth + 1, is_most_common),
-        (false, true) => find_index(values, zeros, depth + 1, is_most_common),
-        (false, false) => find_index(values, ones, depth + 1, is_most_common),
+fn print_trace(filtered: &Filtered) {
+    for step in filtered.trace.iter() {
+        let eliminated = if step.eliminated.len() <= 8 {
+            format!("{:?}", step.eliminated)
+        } else {
+            format!("{} rows", step.eliminated.len())
+        };
+        println!(
+            "  bit {}: {} ones, {} zeros, keep {}, eliminated {}",
+            step.col, step.ones, step.zeros, step.bit as u8, eliminated
+        );
     }
 }
 
@@ -1,34 +1,49 @@
-use crate::util::{parse_lines, read_file_maybe_test, split_parse, BResult, Boom};
+use crate::rng::Rng;
+use crate::util::{self, parse_lines, read_file_maybe_test, split_parse, BResult, Boom};
+use std::collections::HashMap;
 use std::str::FromStr;
 
+#[derive(Clone, Debug)]
 struct CardTemplate<T>
 where
     T: Copy + Default,
 {
-    values: [[T; 5]; 5],
+    width: usize,
+    height: usize,
+    values: Vec<T>,
 }
 
 type Card = CardTemplate<i32>;
 
 impl<T: Copy + Default> CardTemplate<T> {
-    pub fn new() -> CardTemplate<T> {
+    pub fn new(width: usize, height: usize) -> CardTemplate<T> {
         CardTemplate::<T> {
-            values: [[<T as Default>::default(); 5]; 5],
+            width: width,
+            height: height,
+            values: vec![<T as Default>::default(); width * height],
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn get(&self, x: usize, y: usize) -> T {
-        assert!(x < 5, "x >= 5");
-        assert!(y < 5, "y >= 5");
+        assert!(x < self.width, "x >= width");
+        assert!(y < self.height, "y >= height");
 
-        self.values[y][x]
+        self.values[y * self.width + x]
     }
 
     pub fn set(&mut self, x: usize, y: usize, val: T) {
-        assert!(x < 5, "x >= 5");
-        assert!(y < 5, "y >= 5");
+        assert!(x < self.width, "x >= width");
+        assert!(y < self.height, "y >= height");
 
-        self.values[y][x] = val
+        self.values[y * self.width + x] = val
     }
 }
 
@@ -36,7 +51,7 @@ impl FromStr for Card {
     type Err = Boom;
 
     fn from_str(s: &str) -> Result<Card, Boom> {
-        let lines: Vec<String> = parse_lines(s).map_err(Boom::from_display)?;
+        let lines: Vec<String> = parse_lines(s.trim()).map_err(Boom::from_display)?;
 
         let nums = lines
             .iter()
@@ -44,9 +59,18 @@ impl FromStr for Card {
             .collect::<Result<Vec<_>, _>>()
             .map_err(Boom::from_display)?;
 
-        let mut card = Card::new();
-        for y in 0..5 {
-            for x in 0..5 {
+        let height = nums.len();
+        let width = nums.get(0).map(|r| r.len()).unwrap_or(0);
+        if width == 0 || nums.iter().any(|r| r.len() != width) {
+            return Err(Boom::from_display(format!(
+                "card is not rectangular:\n{}",
+                s
+            )));
+        }
+
+        let mut card = Card::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
                 card.set(x, y, nums[y][x])
             }
         }
@@ -55,6 +79,19 @@ impl FromStr for Card {
     }
 }
 
+impl std::fmt::Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height() {
+            let row = (0..self.width())
+                .map(|x| format!("{:>2}", self.get(x, y)))
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
 struct Input {
     pub drawn_numbers: Vec<i32>,
     pub cards: Vec<Card>,
@@ -68,13 +105,14 @@ impl FromStr for Input {
 
         let drawn_numbers = lines[0]
             .split(',')
-            .map(|s| s.parse::<i32>())
+            .map(|s| s.trim().parse::<i32>())
             .collect::<Result<Vec<_>, _>>()
             .map_err(Boom::from_display)?;
 
         let cards = lines
             .iter()
             .skip(1)
+            .filter(|s| !s.trim().is_empty())
             .map(|s| Card::from_str(s))
             .collect::<Result<Vec<_>, _>>()?;
 
@@ -85,152 +123,399 @@ impl FromStr for Input {
     }
 }
 
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let drawn = self
+            .drawn_numbers
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>();
+        writeln!(f, "{}", drawn.join(","))?;
+        for card in self.cards.iter() {
+            write!(f, "\n{}", card)?;
+        }
+        Ok(())
+    }
+}
+
+impl Input {
+    // Cards hold distinct numbers from 0..pool and every number gets drawn
+    // once, in random order, just like the puzzle input.
+    pub fn generate(seed: u64, cards: usize, width: usize, height: usize, pool: i32) -> Input {
+        assert!(
+            width * height <= pool as usize,
+            "pool too small for card size"
+        );
+
+        let mut rng = Rng::from_seed(seed);
+        let mut numbers = (0..pool).collect::<Vec<_>>();
+
+        let cards = (0..cards)
+            .map(|_| {
+                rng.shuffle(&mut numbers);
+                let mut card = Card::new(width, height);
+                for (i, n) in numbers.iter().take(width * height).enumerate() {
+                    card.set(i % width, i / width, *n);
+                }
+                card
+            })
+            .collect();
+
+        rng.shuffle(&mut numbers);
+        Input {
+            drawn_numbers: numbers,
+            cards: cards,
+        }
+    }
+}
+
 type CardMarks = CardTemplate<bool>;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Debug, PartialEq)]
 enum WinCondition {
     Row(usize),
     Col(usize),
+    Diagonal,
+    AntiDiagonal,
+    FourCorners,
+    FullHouse,
+    Mask(String, Vec<(usize, usize)>),
+}
+
+impl WinCondition {
+    // the classic rules: any complete row or column
+    pub fn lines(width: usize, height: usize) -> Vec<WinCondition> {
+        (0..height)
+            .map(WinCondition::Row)
+            .chain((0..width).map(WinCondition::Col))
+            .collect()
+    }
+
+    // lines plus diagonals on square cards and the four corners
+    pub fn extended(width: usize, height: usize) -> Vec<WinCondition> {
+        let mut conditions = WinCondition::lines(width, height);
+        if width == height {
+            conditions.push(WinCondition::Diagonal);
+            conditions.push(WinCondition::AntiDiagonal);
+        }
+        conditions.push(WinCondition::FourCorners);
+        conditions
+    }
+
+    pub fn cells(&self, width: usize, height: usize) -> Vec<(usize, usize)> {
+        match self {
+            WinCondition::Row(y) => (0..width).map(|x| (x, *y)).collect(),
+            WinCondition::Col(x) => (0..height).map(|y| (*x, y)).collect(),
+            WinCondition::Diagonal => (0..width).map(|i| (i, i)).collect(),
+            WinCondition::AntiDiagonal => (0..width).map(|i| (width - 1 - i, i)).collect(),
+            WinCondition::FourCorners => vec![
+                (0, 0),
+                (width - 1, 0),
+                (0, height - 1),
+                (width - 1, height - 1),
+            ],
+            WinCondition::FullHouse => (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .collect(),
+            WinCondition::Mask(_, cells) => cells.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for WinCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WinCondition::Row(y) => write!(f, "row {}", y),
+            WinCondition::Col(x) => write!(f, "column {}", x),
+            WinCondition::Diagonal => write!(f, "diagonal"),
+            WinCondition::AntiDiagonal => write!(f, "anti-diagonal"),
+            WinCondition::FourCorners => write!(f, "four corners"),
+            WinCondition::FullHouse => write!(f, "full house"),
+            WinCondition::Mask(name, _) => write!(f, "{}", name),
+        }
+    }
 }
 
 struct PlayingSession {
     pub card: Card,
     pub marks: CardMarks,
+    conditions: Vec<(WinCondition, Vec<(usize, usize)>)>,
+    cells_by_number: HashMap<i32, Vec<(usize, usize)>>,
 }
 
 impl PlayingSession {
-    pub fn from_card(card: Card) -> PlayingSession {
-        PlayingSession {
-            card: card,
-            marks: CardMarks::new(),
+    // A condition reaching outside the card is an error rather than being
+    // cut down to the cells that fit, which would quietly change the rule.
+    pub fn from_card(card: Card, conditions: &[WinCondition]) -> Result<PlayingSession, Boom> {
+        let (w, h) = (card.width(), card.height());
+
+        let mut cells_by_number: HashMap<i32, Vec<(usize, usize)>> = HashMap::new();
+        for y in 0..h {
+            for x in 0..w {
+                cells_by_number
+                    .entry(card.get(x, y))
+                    .or_default()
+                    .push((x, y));
+            }
         }
+
+        let mut checked = vec![];
+        for c in conditions {
+            let cells = c.cells(w, h);
+            if let Some((x, y)) = cells.iter().find(|(x, y)| *x >= w || *y >= h) {
+                return Err(Boom::from_display(format!(
+                    "{} has cell ({}, {}) outside the {}x{} card",
+                    c, x, y, w, h
+                )));
+            }
+            checked.push((c.clone(), cells));
+        }
+
+        Ok(PlayingSession {
+            card: card,
+            marks: CardMarks::new(w, h),
+            conditions: checked,
+            cells_by_number: cells_by_number,
+        })
     }
 
     pub fn mark_number(&mut self, number: i32) {
-        for y in 0..5 {
-            for x in 0..5 {
-                if self.card.get(x, y) == number {
-                    self.marks.set(x, y, true)
-                }
+        if let Some(cells) = self.cells_by_number.get(&number) {
+            for (x, y) in cells {
+                self.marks.set(*x, *y, true)
             }
         }
     }
 
     pub fn check(&self) -> Option<(i32, WinCondition)> {
-        let conditions = (0..5)
-            .map(|i| [WinCondition::Row(i), WinCondition::Col(i)])
-            .flatten()
-            .collect::<Vec<_>>();
-
-        for condition in conditions {
-            let result = self.check_condition(condition);
-            if result.is_some() {
-                return result.map(|score| (score, condition));
+        for (condition, cells) in self.conditions.iter() {
+            if cells.len() > 0 && cells.iter().all(|(x, y)| self.marks.get(*x, *y)) {
+                return Some((self.unmarked_sum(), condition.clone()));
             }
         }
 
         None
     }
 
-    fn check_condition(&self, condition: WinCondition) -> Option<i32> {
-        let mut marked_count = 0;
-        let coords = match condition {
-            WinCondition::Col(x) => (0..5).map(|y| (x, y)).collect::<Vec<_>>(),
-            WinCondition::Row(y) => (0..5).map(|x| (x, y)).collect::<Vec<_>>(),
-        };
-
-        for (x, y) in coords {
-            if self.marks.get(x, y) {
-                marked_count += 1;
+    fn unmarked_sum(&self) -> i32 {
+        let mut unmarked_sum = 0;
+        for y in 0..self.card.height() {
+            for x in 0..self.card.width() {
+                if !self.marks.get(x, y) {
+                    unmarked_sum += self.card.get(x, y);
+                }
             }
         }
+        unmarked_sum
+    }
+}
 
-        if marked_count == 5 {
-            let mut unmarked_sum = 0;
-            for y in 0..5 {
-                for x in 0..5 {
-                    if !self.marks.get(x, y) {
-                        unmarked_sum += self.card.get(x, y);
-                    }
-                }
+#[derive(Clone, Debug)]
+struct WinEvent {
+    pub card: usize,
+    pub draw: usize,
+    pub number: i32,
+    pub condition: WinCondition,
+    pub score: i32,
+}
+
+impl std::fmt::Display for WinEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "draw {:>3} (number {:>2}): card {:>3} wins with {}, score {}",
+            self.draw, self.number, self.card, self.condition, self.score
+        )
+    }
+}
+
+// Plays every card to completion: each card wins at most once, events come
+// out in draw order and by card index within a draw.
+fn simulate(
+    input: &Input,
+    conditions: impl Fn(usize, usize) -> Vec<WinCondition>,
+) -> Result<Vec<WinEvent>, Boom> {
+    let mut sessions = input
+        .cards
+        .iter()
+        .map(|c| PlayingSession::from_card(c.clone(), &conditions(c.width(), c.height())).map(Some))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut events = vec![];
+    for (draw, num) in input.drawn_numbers.iter().enumerate() {
+        for (card, slot) in sessions.iter_mut().enumerate() {
+            let session = match slot {
+                Some(s) => s,
+                None => continue,
+            };
+            session.mark_number(*num);
+
+            if let Some((unmarked_sum, condition)) = session.check() {
+                events.push(WinEvent {
+                    card: card,
+                    draw: draw,
+                    number: *num,
+                    condition: condition,
+                    score: unmarked_sum * num,
+                });
+                *slot = None;
             }
-            Some(unmarked_sum)
-        } else {
-            None
         }
     }
+    Ok(events)
+}
+
+fn timeline_to_csv(events: &[WinEvent]) -> String {
+    let mut csv = String::from("card,draw,number,condition,score\n");
+    for e in events {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            e.card, e.draw, e.number, e.condition, e.score
+        ));
+    }
+    csv
 }
 
 pub fn run() -> BResult<()> {
     let input_string = read_file_maybe_test("input/day4.txt", "input/day4-test.txt")?;
-
     let input = Input::from_str(&input_string)?;
-    part1(input);
 
-    // recreating because we cannot be bothered to derive Clone
-    let input = Input::from_str(&input_string)?;
-    part2(input);
+    let timeline = simulate(&input, WinCondition::lines)?;
+    if util::has_flag("timeline") {
+        for event in timeline.iter() {
+            println!("{}", event);
+        }
+    }
+    std::fs::create_dir_all("output")?;
+    std::fs::write("output/day4-timeline.csv", timeline_to_csv(&timeline))?;
+
+    part1(&timeline);
+    part2(&timeline);
+    part3(&input)?;
+    part4(&input)?;
+    part5(&input)?;
 
     Ok(())
 }
 
-fn part1(input: Input) {
-    let mut sessions = input
-        .cards
-        .into_iter()
-        .map(PlayingSession::from_card)
-        .collect::<Vec<_>>();
-
-    let mut result: i32 = -1;
-
-    for num in input.drawn_numbers {
-        sessions.iter_mut().for_each(|s| s.mark_number(num));
-
-        let winner = sessions.iter().find(|c| c.check().is_some());
-        match winner {
-            None => (),
-            Some(w) => {
-                let (score, _condition) = w.check().unwrap();
-                result = score * num;
-                break;
-            }
+// -1 when no card ever wins
+fn part1(timeline: &[WinEvent]) {
+    match timeline.first() {
+        Some(first) => {
+            println!("first card won with {}", first.condition);
+            println!("part 1 solution: {}", first.score)
         }
+        None => println!("part 1 solution: -1"),
     }
+}
 
-    println!("part 1 solution: {}", result)
+fn part2(timeline: &[WinEvent]) {
+    match timeline.last() {
+        Some(last) => println!("part 2 solution: {}", last.score),
+        None => println!("part 2 solution: -1"),
+    }
 }
 
-fn part2(input: Input) {
-    let mut sessions = input
-        .cards
-        .into_iter()
-        .map(PlayingSession::from_card)
-        .collect::<Vec<_>>();
+fn part3(input: &Input) -> Result<(), Boom> {
+    // a plus sign through the middle of the card
+    fn cross(width: usize, height: usize) -> WinCondition {
+        let cells = (0..width)
+            .map(|x| (x, height / 2))
+            .chain((0..height).map(|y| (width / 2, y)))
+            .collect();
+        WinCondition::Mask("cross".to_string(), cells)
+    }
 
-    let mut result: i32 = -1;
+    let timeline = simulate(input, |w, h| {
+        let mut conditions = WinCondition::extended(w, h);
+        conditions.push(cross(w, h));
+        conditions.push(WinCondition::FullHouse);
+        conditions
+    })?;
 
-    for num in input.drawn_numbers {
-        sessions.iter_mut().for_each(|s| s.mark_number(num));
+    if let Some(first) = timeline.first() {
+        println!("extended rules: {}", first);
+    }
+    Ok(())
+}
 
-        let winners = sessions
-            .iter()
-            .enumerate()
-            .filter(|(_, c)| c.check().is_some())
-            .collect::<Vec<_>>();
-        if winners.len() > 0 {
-            for (_, w) in winners.iter() {
-                let (score, _condition) = w.check().unwrap();
-                result = score * num;
-            }
+// Generates puzzles of several shapes, checks they survive a round trip
+// through the text format and that every card eventually wins.
+fn part4(input: &Input) -> BResult<()> {
+    for (seed, (w, h)) in [(5, 5), (3, 3), (7, 4), (1, 6)].iter().enumerate() {
+        let generated = Input::generate(seed as u64, input.cards.len(), *w, *h, 100);
+        let text = generated.to_string();
+        let parsed = Input::from_str(&text)?;
+
+        let timeline = simulate(&parsed, WinCondition::lines)?;
+        println!(
+            "generated {}x{}: {} of {} cards won, first on draw {}, last on draw {}",
+            w,
+            h,
+            timeline.len(),
+            parsed.cards.len(),
+            timeline.first().map(|e| e.draw).unwrap_or(0),
+            timeline.last().map(|e| e.draw).unwrap_or(0)
+        );
+        if seed == 0 {
+            std::fs::write("output/day4-generated.txt", text)?;
+        }
+    }
+    Ok(())
+}
 
-            let mut to_remove = winners.into_iter().map(|(i, _)| i).collect::<Vec<_>>();
-            to_remove.sort();
-            to_remove.reverse();
-            for i in to_remove {
-                sessions.remove(i);
+// Monte Carlo over shuffled draw orders: how often each card is the first
+// to win, and how long it takes on average to complete.
+fn part5(input: &Input) -> Result<(), Boom> {
+    let trials = util::flag_value("trials")
+        .and_then(|t| t.parse::<usize>().ok())
+        .unwrap_or(200);
+
+    let mut rng = Rng::from_seed(2021);
+    let mut shuffled = input.clone();
+    let mut first_wins = vec![0.0f64; input.cards.len()];
+    let mut win_turns = vec![0usize; input.cards.len()];
+    let mut win_counts = vec![0usize; input.cards.len()];
+
+    for _ in 0..trials {
+        rng.shuffle(&mut shuffled.drawn_numbers);
+        let timeline = simulate(&shuffled, WinCondition::lines)?;
+
+        if let Some(first) = timeline.first() {
+            // cards completing on the same draw share the win
+            let tied = timeline.iter().filter(|e| e.draw == first.draw).count();
+            for e in timeline.iter().take(tied) {
+                first_wins[e.card] += 1.0 / tied as f64;
             }
         }
+        for e in timeline.iter() {
+            win_turns[e.card] += e.draw;
+            win_counts[e.card] += 1;
+        }
+    }
+
+    let mut ranking = (0..input.cards.len()).collect::<Vec<_>>();
+    ranking.sort_by(|a, b| first_wins[*b].partial_cmp(&first_wins[*a]).unwrap());
+
+    println!("monte carlo over {} draw orders:", trials);
+    for card in ranking.iter().take(5) {
+        println!(
+            "  card {:>3}: wins first {:>5.1}%, expected win on draw {:.1}",
+            card,
+            100.0 * first_wins[*card] / trials as f64,
+            win_turns[*card] as f64 / std::cmp::max(win_counts[*card], 1) as f64
+        );
     }
 
-    println!("part 2 solution: {}", result)
+    let actual = simulate(input, WinCondition::lines)?;
+    if let Some(first) = actual.first() {
+        let place = ranking.iter().position(|c| *c == first.card).unwrap() + 1;
+        println!(
+            "  the real draw's winner, card {}, ranks {} of {}",
+            first.card,
+            place,
+            ranking.len()
+        );
+    }
+    Ok(())
 }
@@ -1,7 +1,10 @@
+use crate::grid::{self, Grid, LineKind, LinePoints, Point};
+use crate::svg::Svg;
 use crate::util::{parse_lines, read_file_maybe_test, BResult, Boom};
 use image::{ImageBuffer, Luma};
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 #[derive(Debug)]
@@ -12,39 +15,26 @@ struct VentLine {
     pub y2: usize,
 }
 
-impl VentLine {
-    pub fn all_coords(&self) -> Vec<(usize, usize)> {
-        if self.x1 == self.x2 {
-            let (y1, y2) = if self.y1 > self.y2 {
-                (self.y2, self.y1)
-            } else {
-                (self.y1, self.y2)
-            };
-            return (y1..=y2).map(|y| (self.x1, y)).collect::<Vec<_>>();
-        }
+#[derive(Clone, Copy, Debug)]
+enum LineFilter {
+    AxisAligned,
+    AxisOrDiagonal,
+    All,
+}
 
-        if self.y1 == self.y2 {
-            let (x1, x2) = if self.x1 > self.x2 {
-                (self.x2, self.x1)
-            } else {
-                (self.x1, self.x2)
-            };
-            return (x1..=x2).map(|x| (x, self.y1)).collect::<Vec<_>>();
-        }
-
-        if (self.y2 as i32 - self.y1 as i32).abs() == (self.x2 as i32 - self.x1 as i32).abs() {
-            let ((x1, y1), (x2, y2)) = if self.x1 > self.x2 {
-                ((self.x2, self.y2), (self.x1, self.y1))
-            } else {
-                ((self.x1, self.y1), (self.x2, self.y2))
-            };
-            let ystep: i32 = if y2 > y1 { 1 } else { -1 };
-            return (x1..=x2)
-                .map(|x| (x, (y1 as i32 + ystep * (x as i32 - x1 as i32)) as usize))
-                .collect::<Vec<_>>();
+impl LineFilter {
+    pub fn accepts(&self, kind: LineKind) -> bool {
+        match self {
+            LineFilter::AxisAligned => kind == LineKind::Horizontal || kind == LineKind::Vertical,
+            LineFilter::AxisOrDiagonal => kind != LineKind::Sloped,
+            LineFilter::All => true,
         }
+    }
+}
 
-        vec![]
+impl VentLine {
+    pub fn rasterize(&self) -> (LineKind, LinePoints) {
+        grid::rasterize(Point(self.x1, self.y1), Point(self.x2, self.y2))
     }
 }
 
@@ -79,60 +69,373 @@ struct Input {
     pub vent_lines: Vec<VentLine>,
 }
 
+// One counting layer per filter, all filled from a single pass over the lines.
+struct OverlapMap {
+    layers: Vec<(LineFilter, Grid<i32>)>,
+}
+
+impl OverlapMap {
+    pub fn from_lines(lines: &[VentLine], filters: &[LineFilter]) -> OverlapMap {
+        let mut max_x = 0;
+        let mut max_y = 0;
+        for line in lines.iter() {
+            max_x = std::cmp::max(max_x, std::cmp::max(line.x1, line.x2));
+            max_y = std::cmp::max(max_y, std::cmp::max(line.y1, line.y2));
+        }
+
+        let mut layers = filters
+            .iter()
+            .map(|f| (*f, Grid::<i32>::from_size(max_x + 1, max_y + 1)))
+            .collect::<Vec<_>>();
+
+        for line in lines.iter() {
+            let (kind, points) = line.rasterize();
+            if kind == LineKind::Sloped {
+                println!("warning: {:?} is neither straight nor diagonal", line);
+            }
+
+            let accepted = layers
+                .iter()
+                .map(|(filter, _)| filter.accepts(kind))
+                .collect::<Vec<_>>();
+            for point in points {
+                for ((_, grid), accepts) in layers.iter_mut().zip(accepted.iter()) {
+                    if *accepts {
+                        grid.set(point, grid.get(point) + 1);
+                    }
+                }
+            }
+        }
+
+        OverlapMap { layers: layers }
+    }
+
+    pub fn layer(&self, index: usize) -> &Grid<i32> {
+        &self.layers[index].1
+    }
+
+    pub fn count_overlaps(&self, index: usize) -> usize {
+        self.layer(index).iter().filter(|x| **x > 1).count()
+    }
+}
+
+// The four directions a straight or diagonal vent can run in. Each one is a
+// family of parallel lines `a*x + b*y = key`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Family {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    AntiDiagonal,
+}
+
+const FAMILIES: [Family; 4] = [
+    Family::Horizontal,
+    Family::Vertical,
+    Family::Diagonal,
+    Family::AntiDiagonal,
+];
+
+impl Family {
+    fn coefficients(&self) -> (i64, i64) {
+        match self {
+            Family::Horizontal => (0, 1),
+            Family::Vertical => (1, 0),
+            Family::Diagonal => (1, -1),
+            Family::AntiDiagonal => (1, 1),
+        }
+    }
+
+    fn key(&self, (x, y): (i64, i64)) -> i64 {
+        let (a, b) = self.coefficients();
+        a * x + b * y
+    }
+
+    // position along the line: y for verticals, x for everything else
+    fn param(&self, (x, y): (i64, i64)) -> i64 {
+        match self {
+            Family::Vertical => y,
+            _ => x,
+        }
+    }
+}
+
+// A vent line as a closed range of `param` on the line `family`/`key`.
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    family: Family,
+    key: i64,
+    lo: i64,
+    hi: i64,
+}
+
+impl VentLine {
+    fn run(&self) -> Option<Run> {
+        let start = (self.x1 as i64, self.y1 as i64);
+        let end = (self.x2 as i64, self.y2 as i64);
+        let family = match self.rasterize().0 {
+            LineKind::Horizontal => Family::Horizontal,
+            LineKind::Vertical => Family::Vertical,
+            LineKind::Diagonal if (end.0 - start.0) * (end.1 - start.1) > 0 => Family::Diagonal,
+            LineKind::Diagonal => Family::AntiDiagonal,
+            LineKind::Sloped => return None,
+        };
+
+        let (p1, p2) = (family.param(start), family.param(end));
+        Some(Run {
+            family: family,
+            key: family.key(start),
+            lo: std::cmp::min(p1, p2),
+            hi: std::cmp::max(p1, p2),
+        })
+    }
+}
+
+// Sorted, disjoint ranges covered by at least `depth` of the given ranges.
+fn covered_at_least(ranges: &[(i64, i64)], depth: i32) -> Vec<(i64, i64)> {
+    let mut events = ranges
+        .iter()
+        .flat_map(|(lo, hi)| [(*lo, 1), (*hi + 1, -1)])
+        .collect::<Vec<_>>();
+    events.sort();
+
+    let mut result: Vec<(i64, i64)> = vec![];
+    let mut active = 0;
+    let mut start = 0;
+    for (at, change) in events {
+        let was_covered = active >= depth;
+        active += change;
+        let is_covered = active >= depth;
+        if !was_covered && is_covered {
+            start = at;
+        } else if was_covered && !is_covered {
+            match result.last_mut() {
+                Some(last) if last.1 + 1 == start => last.1 = at - 1,
+                _ => result.push((start, at - 1)),
+            }
+        }
+    }
+    result
+}
+
+fn range_contains(ranges: &[(i64, i64)], value: i64) -> bool {
+    let i = ranges.partition_point(|(_, hi)| *hi < value);
+    i < ranges.len() && ranges[i].0 <= value
+}
+
+fn intersect_lines(f1: Family, k1: i64, f2: Family, k2: i64) -> Option<(i64, i64)> {
+    let (a1, b1) = f1.coefficients();
+    let (a2, b2) = f2.coefficients();
+    let det = a1 * b2 - a2 * b1;
+    let x = k1 * b2 - k2 * b1;
+    let y = a1 * k2 - a2 * k1;
+    if det == 0 || x % det != 0 || y % det != 0 {
+        return None;
+    }
+    Some((x / det, y / det))
+}
+
+// whether `point` lies on the given family's line through it, within its runs
+fn covers(
+    runs: &HashMap<(Family, i64), Vec<(i64, i64)>>,
+    family: Family,
+    point: (i64, i64),
+) -> bool {
+    match runs.get(&(family, family.key(point))) {
+        Some(ranges) => range_contains(ranges, family.param(point)),
+        None => false,
+    }
+}
+
+// Counts overlap points straight from the segments. Collinear overlaps are
+// swept as 1-D intervals per line; lines from different families can only
+// meet in single points, which are checked against the sweeps as they're
+// found. At most one line per family goes through a point, so a point where
+// several lines cross is only counted by the pair of its first two families
+// and no set of points seen is needed. Memory grows with the number of lines,
+// not the area or the number of crossings.
+fn sweep_overlaps(lines: &[VentLine], filter: LineFilter) -> usize {
+    let mut by_line: HashMap<(Family, i64), Vec<(i64, i64)>> = HashMap::new();
+    for line in lines {
+        let kind = line.rasterize().0;
+        if !filter.accepts(kind) {
+            continue;
+        }
+        match line.run() {
+            Some(run) => by_line
+                .entry((run.family, run.key))
+                .or_default()
+                .push((run.lo, run.hi)),
+            None => println!("warning: sweep skips {:?}", line),
+        }
+    }
+
+    let covered = by_line
+        .iter()
+        .map(|(line, ranges)| (*line, covered_at_least(ranges, 1)))
+        .collect::<HashMap<_, _>>();
+    let doubled = by_line
+        .iter()
+        .map(|(line, ranges)| (*line, covered_at_least(ranges, 2)))
+        .collect::<HashMap<_, _>>();
+
+    let swept: i64 = doubled
+        .values()
+        .map(|ranges| ranges.iter().map(|(lo, hi)| hi - lo + 1).sum::<i64>())
+        .sum();
+
+    // a crossing no sweep saw is a new overlap; one that several sweeps saw
+    // was counted once too often for every extra family
+    let mut adjustment: i64 = 0;
+    let mut keys = covered.keys().collect::<Vec<_>>();
+    keys.sort();
+    for (i, (f1, k1)) in keys.iter().enumerate() {
+        for (f2, k2) in keys.iter().skip(i + 1) {
+            if f1 == f2 {
+                continue;
+            }
+            let point = match intersect_lines(*f1, *k1, *f2, *k2) {
+                Some(p) => p,
+                None => continue,
+            };
+            if !covers(&covered, *f1, point) || !covers(&covered, *f2, point) {
+                continue;
+            }
+            // keys are sorted by family, so f1 comes before f2
+            let first_two = FAMILIES
+                .iter()
+                .filter(|f| covers(&covered, **f, point))
+                .take(2)
+                .collect::<Vec<_>>();
+            if first_two != [f1, f2] {
+                continue;
+            }
+
+            let sweeps = FAMILIES
+                .iter()
+                .filter(|f| covers(&doubled, **f, point))
+                .count() as i64;
+            adjustment += if sweeps == 0 { 1 } else { 1 - sweeps };
+        }
+    }
+
+    (swept + adjustment) as usize
+}
+
 pub fn run() -> BResult<()> {
     let input_string = read_file_maybe_test("input/day5.txt", "input/day5-test.txt")?;
     let input = Input {
         vent_lines: parse_lines(&input_string)?,
     };
 
-    part1(&input);
-    part2(&input);
+    let map = OverlapMap::from_lines(
+        &input.vent_lines,
+        &[
+            LineFilter::AxisAligned,
+            LineFilter::AxisOrDiagonal,
+            LineFilter::All,
+        ],
+    );
 
-    Ok(())
-}
+    part1(&map);
+    part2(&map)?;
+    part3(&map);
+    part4(&input);
 
-fn part1(input: &Input) {
-    let mut max_x = 0;
-    let mut max_y = 0;
-    for line in input.vent_lines.iter() {
-        max_x = std::cmp::max(max_x, std::cmp::max(line.x1, line.x2));
-        max_y = std::cmp::max(max_y, std::cmp::max(line.y1, line.y2));
-    }
+    paint_svg(&input.vent_lines, map.layer(1))?;
 
-    let mut vent_map = vec![vec![0; max_x + 1]; max_y + 1];
-    for line in input.vent_lines.iter() {
-        for (x, y) in line.all_coords() {
-            vent_map[y][x] = vent_map[y][x] + 1
-        }
-    }
+    Ok(())
+}
 
-    let max_depth = vent_map.iter().flatten().max().unwrap();
-    dbg!(max_depth);
+fn part1(map: &OverlapMap) {
+    println!("part 1 solution: {}", map.count_overlaps(0))
+}
 
-    let max_count = vent_map.iter().flatten().filter(|x| **x > 1).count();
+fn part2(map: &OverlapMap) -> BResult<()> {
+    println!("part 2 solution: {}", map.count_overlaps(1));
 
-    paint(&vent_map, *max_depth).unwrap();
+    let all = map.layer(1);
+    let max_depth = all.iter().max().copied().unwrap_or(0).max(1);
+    paint(all, max_depth)
+}
 
-    println!("part 1 solution: {}", max_count)
+fn part3(map: &OverlapMap) {
+    println!("overlaps including sloped lines: {}", map.count_overlaps(2));
 }
 
-fn part2(_input: &Input) {
-    println!("part 2 solution: {}", "oops")
+fn part4(input: &Input) {
+    println!(
+        "sweep: axis-aligned {}, with diagonals {}",
+        sweep_overlaps(&input.vent_lines, LineFilter::AxisAligned),
+        sweep_overlaps(&input.vent_lines, LineFilter::AxisOrDiagonal)
+    );
+
+    // far too large for a dense map
+    let scaled = input
+        .vent_lines
+        .iter()
+        .map(|l| VentLine {
+            x1: l.x1 * 1000,
+            y1: l.y1 * 1000,
+            x2: l.x2 * 1000,
+            y2: l.y2 * 1000,
+        })
+        .collect::<Vec<_>>();
+    println!(
+        "sweep, coordinates scaled by 1000: {}",
+        sweep_overlaps(&scaled, LineFilter::AxisOrDiagonal)
+    );
 }
 
-fn paint(map: &Vec<Vec<i32>>, max_depth: i32) -> BResult<()> {
+fn paint(map: &Grid<i32>, max_depth: i32) -> BResult<()> {
+    std::fs::create_dir_all("output")?;
     let img = ImageBuffer::from_fn(
-        map[0].len().try_into().unwrap(),
-        map.len().try_into().unwrap(),
-        |x, y| {
-            Luma([(map[y as usize][x as usize] * 255 / max_depth) as u8])
-            /*if x % 2 == 0 {
-                Luma([0u8])
-            } else {
-                Luma([255u8])
-            }>*/
-        },
+        map.width().try_into().unwrap(),
+        map.height().try_into().unwrap(),
+        |x, y| Luma([(map.get(Point(x as usize, y as usize)) * 255 / max_depth) as u8]),
     );
 
     Ok(img.save("output/day5.png")?)
 }
+
+fn paint_svg(lines: &[VentLine], overlaps: &Grid<i32>) -> BResult<()> {
+    fn overlap_colour(count: i32) -> &'static str {
+        match count {
+            2 => "gold",
+            3 => "orange",
+            4 => "red",
+            _ => "purple",
+        }
+    }
+
+    let mut svg = Svg::new(overlaps.width() as f64, overlaps.height() as f64);
+    svg.apply_args();
+    svg.background("white");
+
+    for line in lines {
+        let colour = match line.rasterize().0 {
+            LineKind::Horizontal | LineKind::Vertical => "steelblue",
+            LineKind::Diagonal => "seagreen",
+            LineKind::Sloped => "grey",
+        };
+        svg.line(
+            line.x1 as f64,
+            line.y1 as f64,
+            line.x2 as f64,
+            line.y2 as f64,
+            colour,
+            0.3,
+        );
+    }
+
+    for y in 0..overlaps.height() {
+        for x in 0..overlaps.width() {
+            let count = *overlaps.get(Point(x, y));
+            if count > 1 {
+                svg.circle(x as f64, y as f64, 0.4, overlap_colour(count));
+            }
+        }
+    }
+
+    svg.save("output/day5.svg")
+}
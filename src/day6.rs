@@ -1,33 +1,78 @@
-use crate::util::{read_file_maybe_test, split_parse, BResult, Boom};
+use crate::matrix::Matrix;
+use crate::util::{self, read_file_maybe_test, split_parse, BResult, Boom};
+
+#[derive(Clone, Copy, Debug)]
+struct Lifecycle {
+    pub reset_timer: usize,
+    pub newborn_timer: usize,
+    pub offspring: u64,
+}
+
+impl Lifecycle {
+    pub fn standard() -> Lifecycle {
+        Lifecycle {
+            reset_timer: 6,
+            newborn_timer: 8,
+            offspring: 1,
+        }
+    }
+
+    // the standard lifecycle with `--reset=`, `--newborn=` and `--offspring=`
+    // swapped in where given
+    pub fn from_flags() -> Lifecycle {
+        let standard = Lifecycle::standard();
+        Lifecycle {
+            reset_timer: util::flag_value("reset")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(standard.reset_timer),
+            newborn_timer: util::flag_value("newborn")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(standard.newborn_timer),
+            offspring: util::flag_value("offspring")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(standard.offspring),
+        }
+    }
 
-const MAX_GEN: usize = 8;
-const BIRTH_GEN: usize = 6;
-const TOTAL_GENS: usize = 9;
+    pub fn max_gen(&self) -> usize {
+        std::cmp::max(self.reset_timer, self.newborn_timer)
+    }
 
-#[derive(Debug)]
+    pub fn total_gens(&self) -> usize {
+        self.max_gen() + 1
+    }
+}
+
+#[derive(Clone, Debug)]
 struct FishSpectrum {
-    counts: [u64; TOTAL_GENS],
+    lifecycle: Lifecycle,
+    counts: Vec<u64>,
 }
 
 impl FishSpectrum {
-    pub fn new() -> FishSpectrum {
+    pub fn new(lifecycle: Lifecycle) -> FishSpectrum {
         FishSpectrum {
-            counts: [0; TOTAL_GENS],
+            lifecycle: lifecycle,
+            counts: vec![0; lifecycle.total_gens()],
         }
     }
 
-    pub fn from_vec<T: Copy + TryInto<usize>>(input: &Vec<T>) -> Result<FishSpectrum, Boom>
+    pub fn from_vec<T: Copy + TryInto<usize>>(
+        lifecycle: Lifecycle,
+        input: &Vec<T>,
+    ) -> Result<FishSpectrum, Boom>
     where
         T::Error: std::fmt::Debug,
     {
-        let mut spectrum = FishSpectrum::new();
+        let mut spectrum = FishSpectrum::new(lifecycle);
+        let max_gen = lifecycle.max_gen();
 
         for fish_value in input {
             let fish: usize = (*fish_value).try_into().unwrap();
-            if fish > MAX_GEN {
+            if fish > max_gen {
                 return Err(Boom::from_display(format!(
                     "generation cannot be larger than {} (was {})",
-                    MAX_GEN, fish
+                    max_gen, fish
                 )));
             }
             spectrum.counts[fish] += 1;
@@ -36,19 +81,68 @@ impl FishSpectrum {
         Ok(spectrum)
     }
 
-    pub fn update(&self) -> FishSpectrum {
+    // None once the population no longer fits in a u64
+    pub fn update(&self) -> Option<FishSpectrum> {
         let births = self.counts[0];
-        let mut new_spectrum = FishSpectrum::new();
-        for i in 0..MAX_GEN {
+        let newborns = births.checked_mul(self.lifecycle.offspring)?;
+        let mut new_spectrum = FishSpectrum::new(self.lifecycle);
+        for i in 0..self.lifecycle.max_gen() {
             new_spectrum.counts[i] = self.counts[i + 1];
         }
-        new_spectrum.counts[BIRTH_GEN] += births;
-        new_spectrum.counts[MAX_GEN] += births;
+        let reset = &mut new_spectrum.counts[self.lifecycle.reset_timer];
+        *reset = reset.checked_add(births)?;
+        let newborn = &mut new_spectrum.counts[self.lifecycle.newborn_timer];
+        *newborn = newborn.checked_add(newborns)?;
+
         new_spectrum
+            .counts
+            .iter()
+            .try_fold(0u64, |acc, x| acc.checked_add(*x))?;
+        Some(new_spectrum)
+    }
+
+    // the same step as `update`, as a linear map on the counts
+    pub fn transition_matrix(&self) -> Matrix {
+        let size = self.lifecycle.total_gens();
+        let mut m = Matrix::zero(size, size);
+        for i in 0..self.lifecycle.max_gen() {
+            m.set(i, i + 1, 1);
+        }
+        m.add_at(self.lifecycle.reset_timer, 0, 1);
+        m.add_at(self.lifecycle.newborn_timer, 0, self.lifecycle.offspring);
+        m
+    }
+
+    pub fn checked_len_after(&self, days: u64) -> Option<u64> {
+        let counts = self
+            .transition_matrix()
+            .checked_pow(days)?
+            .checked_apply(&self.counts)?;
+        counts.iter().try_fold(0u64, |acc, x| acc.checked_add(*x))
+    }
+
+    pub fn len_after_mod(&self, days: u64, modulus: u64) -> u64 {
+        let counts = self
+            .transition_matrix()
+            .pow_mod(days, modulus)
+            .apply_mod(&self.counts, modulus);
+        counts.iter().fold(0u64, |acc, x| (acc + x) % modulus)
+    }
+
+    // the starting spectrum followed by the one after every day
+    pub fn time_series(&self, days: usize) -> Result<Vec<FishSpectrum>, Boom> {
+        let mut series = vec![self.clone()];
+        for day in 1..=days {
+            let next = series.last().unwrap().update().ok_or_else(|| {
+                Boom::from_display(format!("population overflows a u64 on day {}", day))
+            })?;
+            series.push(next);
+        }
+        Ok(series)
     }
 
     pub fn show(&self) -> String {
-        let gens = (0..MAX_GEN)
+        let gens = (0..self.counts.len())
             .map(|x| std::iter::repeat(x).take(self.counts[x] as usize))
             .flatten()
             .map(|x| x.to_string())
@@ -61,6 +155,24 @@ impl FishSpectrum {
     }
 }
 
+fn series_to_csv(series: &[FishSpectrum]) -> String {
+    let total_gens = series.first().map(|s| s.counts.len()).unwrap_or(0);
+    let mut csv = String::from("day,total");
+    for gen in 0..total_gens {
+        csv.push_str(&format!(",timer{}", gen));
+    }
+    csv.push('\n');
+
+    for (day, spectrum) in series.iter().enumerate() {
+        csv.push_str(&format!("{},{}", day, spectrum.len()));
+        for count in spectrum.counts.iter() {
+            csv.push_str(&format!(",{}", count));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
 impl std::fmt::Display for FishSpectrum {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.show())
@@ -71,30 +183,68 @@ pub fn run() -> BResult<()> {
     let input_string = read_file_maybe_test("input/day6.txt", "input/day6-test.txt")?;
     let input = split_parse::<i32>(&input_string, regex::Regex::new(",")?)?;
 
-    part1(&input);
-    part2(&input);
+    part1(&input)?;
+    part2(&input)?;
+    part3(&input);
+    part4(&input)?;
 
     Ok(())
 }
 
-fn part1(input: &Vec<i32>) {
-    let mut fish = FishSpectrum::from_vec(input).unwrap();
+fn part1(input: &Vec<i32>) -> BResult<()> {
+    let fish = FishSpectrum::from_vec(Lifecycle::standard(), input)?;
 
     println!("Initial state:   \t{}", fish);
-    for _ in 1..=80 {
-        fish = fish.update();
-    }
+    let series = fish.time_series(80)?;
 
-    println!("part 1 solution: {}", fish.len());
+    println!("part 1 solution: {}", series.last().unwrap().len());
+    Ok(())
+}
+
+fn part2(input: &Vec<i32>) -> BResult<()> {
+    let fish = FishSpectrum::from_vec(Lifecycle::standard(), input)?;
+    let series = fish.time_series(256)?;
+
+    println!("part 2 solution: {}", series.last().unwrap().len());
+    Ok(())
 }
 
-fn part2(input: &Vec<i32>) {
-    let mut fish = FishSpectrum::from_vec(input).unwrap();
+fn part3(input: &Vec<i32>) {
+    const MODULUS: u64 = 1_000_000_007;
 
-    for _ in 1..=256 {
-        fish = fish.update();
-        //println!("Generation {} fish count {}", i, fish.len());
+    let fish = FishSpectrum::from_vec(Lifecycle::standard(), input).unwrap();
+
+    println!("after 256 days: {:?}", fish.checked_len_after(256));
+    println!("after 1000 days: {:?}", fish.checked_len_after(1000));
+    for days in [1_000_000u64, 1_000_000_000_000] {
+        println!(
+            "after {} days, modulo {}: {}",
+            days,
+            MODULUS,
+            fish.len_after_mod(days, MODULUS)
+        );
     }
+}
 
-    println!("part 2 solution: {}", fish.len());
+// The lifecycle from the command line, run for `--days=N` (80 by default)
+// with the population on every day written out as CSV.
+fn part4(input: &Vec<i32>) -> BResult<()> {
+    let lifecycle = Lifecycle::from_flags();
+    let days = util::flag_value("days")
+        .and_then(|d| d.parse::<usize>().ok())
+        .unwrap_or(80);
+
+    let fish = FishSpectrum::from_vec(lifecycle, input)?;
+    let series = fish.time_series(days)?;
+    println!(
+        "{:?} after {} days: {}",
+        lifecycle,
+        days,
+        series.last().unwrap().len()
+    );
+
+    std::fs::create_dir_all("output")?;
+    std::fs::write("output/day6.csv", series_to_csv(&series))?;
+
+    Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::optimize::{self, AxisCostFn, Crab};
 use crate::util::{self, BResult};
 
 type Input = [i32];
@@ -9,24 +10,19 @@ pub fn run() -> BResult<()> {
     part1(&input);
     part2(&input);
     part3(&input);
+    part4(&input)?;
 
     Ok(())
 }
 
 fn part1(input: &Input) {
-    println!(
-        "part 1 solution: {}",
-        generic_min_distance(input, |x, target| (x - target).abs())
-    );
+    let best = optimize::linear_optimum(input);
+    println!("part 1 solution: {} (at {})", best.cost, best.position);
 }
 
 fn part2(input: &Input) {
-    fn sqr_dist(x1: i32, x2: i32) -> i32 {
-        let abs = (x2 - x1).abs();
-        abs * (abs + 1) / 2
-    }
-
-    println!("part 2 solution: {}", generic_min_distance(input, sqr_dist));
+    let best = optimize::triangular_optimum(input);
+    println!("part 2 solution: {} (at {})", best.cost, best.position);
 }
 
 fn part3(input: &Input) {
@@ -39,32 +35,77 @@ fn part3(input: &Input) {
         abs * (abs + 1) / 2
     }
 
-    let (min_mad, _) = generic_min_distance_value(input, mad_dist);
-    let (min_sqr, _) = generic_min_distance_value(input, sqr_dist);
-    let mean = (input.iter().sum::<i32>() as f64) / input.len() as f64;
-    let median = util::median(&mut input.iter().map(|x| *x).collect::<Vec<i32>>());
-
-    dbg!(min_mad, min_sqr, mean, median);
-}
-
-fn generic_min_distance_value(input: &Input, distance_fn: impl Fn(i32, i32) -> i32) -> (i32, i32) {
-    let min = *input.iter().min().unwrap();
-    let max = *input.iter().max().unwrap();
-
-    let distances: Vec<(i32, i32)> = (min..=max)
-        .map(|target| (target, input.iter().map(|x| distance_fn(*x, target)).sum()))
-        .collect();
-
-    *distances.iter().min_by_key(|(_, x)| x).unwrap()
+    let ones = vec![1; input.len()];
+    let (brute_mad_pos, brute_mad) =
+        optimize::generic_min_distance_value(input, &ones, |x, t| mad_dist(x, t) as i64).unwrap();
+    let (brute_sqr_pos, brute_sqr) =
+        optimize::generic_min_distance_value(input, &ones, |x, t| sqr_dist(x, t) as i64).unwrap();
+    println!("brute force linear: {} (at {})", brute_mad, brute_mad_pos);
+    println!(
+        "brute force triangular: {} (at {})",
+        brute_sqr, brute_sqr_pos
+    );
+    println!(
+        "ternary search linear: {:?}",
+        optimize::convex_optimum(input, mad_dist)
+    );
+    println!(
+        "ternary search triangular: {:?}",
+        optimize::convex_optimum(input, sqr_dist)
+    );
 }
 
-fn generic_min_distance(input: &Input, distance_fn: impl Fn(i32, i32) -> i32) -> i32 {
-    let min = *input.iter().min().unwrap();
-    let max = *input.iter().max().unwrap();
+// Pretends consecutive input numbers are coordinates of crabs in 2-D and
+// 3-D, with weights cycling through 1..=3.
+fn part4(input: &Input) -> BResult<()> {
+    fn crabs<const D: usize>(input: &Input) -> Vec<Crab<D>> {
+        input
+            .chunks_exact(D)
+            .enumerate()
+            .map(|(i, chunk)| Crab {
+                position: chunk.try_into().unwrap(),
+                weight: 1 + (i % 3) as i64,
+            })
+            .collect()
+    }
 
-    let distances: Vec<i32> = (min..=max)
-        .map(|target| input.iter().map(|x| distance_fn(*x, target)).sum())
-        .collect();
+    let cost_fns = [
+        AxisCostFn::Linear,
+        AxisCostFn::Triangular,
+        AxisCostFn::Quadratic,
+        AxisCostFn::Capped(Box::new(AxisCostFn::Triangular), 100),
+        "min(d * d, 50) + d / 2".parse::<AxisCostFn>()?,
+        AxisCostFn::Closure(Box::new(|d| if d > 0 { 10 + d } else { 0 })),
+    ];
+
+    let planar = crabs::<2>(input);
+    let spatial = crabs::<3>(input);
+    for cost_fn in cost_fns.iter() {
+        let (a2, a3) = match (
+            optimize::align(&planar, cost_fn),
+            optimize::align(&spatial, cost_fn),
+        ) {
+            (Some(a2), Some(a3)) => (a2, a3),
+            _ => {
+                println!("{:?}: not enough input for 2-D and 3-D crabs", cost_fn);
+                continue;
+            }
+        };
+        println!(
+            "{:?}: 2-D {:?} cost {}, 3-D {:?} cost {}",
+            cost_fn, a2.position, a2.cost, a3.position, a3.cost
+        );
+        let (worst, worst_cost) = a2
+            .crab_costs
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, c)| **c)
+            .unwrap();
+        println!(
+            "  most expensive 2-D crab: {:?} paying {} over both axes",
+            planar[worst], worst_cost
+        );
+    }
 
-    *distances.iter().min().unwrap()
+    Ok(())
 }
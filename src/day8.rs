@@ -1,6 +1,6 @@
+use crate::rng::Rng;
 use crate::util::{self, BResult, Boom};
 use std::collections::HashMap;
-use std::convert::TryInto;
 use std::str::FromStr;
 
 /******************
@@ -27,21 +27,22 @@ b    .  b    .  .    c  b    c  b    c
 
 #[derive(Debug)]
 struct DisplayNote {
-    pub input: [String; 10],
-    pub output: [String; 4],
+    pub input: Vec<String>,
+    pub output: Vec<String>,
 }
 
 impl FromStr for DisplayNote {
     type Err = Boom;
 
     fn from_str(s: &str) -> Result<DisplayNote, Boom> {
-        let parts = s.split(" | ").collect::<Vec<_>>();
-        let input: [String; 10] = sized_string(parts[0]);
-        let output: [String; 4] = sized_string(parts[1]);
+        let parts = s.split('|').collect::<Vec<_>>();
+        if parts.len() != 2 {
+            return Err(Boom::from_display(format!("expected one | in note {}", s)));
+        }
 
         Ok(DisplayNote {
-            input: input,
-            output: output,
+            input: words(parts[0]),
+            output: words(parts[1]),
         })
     }
 }
@@ -52,8 +53,14 @@ pub fn run() -> BResult<()> {
     let input_string = util::read_file_maybe_test("input/day8.txt", "input/day8-test.txt")?;
     let input = util::parse_lines::<DisplayNote>(&input_string)?;
 
+    let font_path =
+        util::flag_value("font").unwrap_or_else(|| "input/fonts/seven-segment.txt".to_string());
+    let font = Font::load(&font_path)?;
+
     part1(&input);
-    part2(&input);
+    part2(&input, &font);
+    part3(&input, &font);
+    part4()?;
 
     Ok(())
 }
@@ -94,164 +101,572 @@ mod display_segment {
     pub const COUNT: usize = 7;
 }
 
-fn part2(notes: &Input) {
+fn find_by_length(digits: &[String], length: usize) -> Vec<usize> {
+    digits
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| x.len() == length)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn one_by_length(digits: &[String], length: usize) -> Option<usize> {
+    find_by_length(digits, length).first().copied()
+}
+
+// The hand-derived deduction: needs all ten patterns of a standard display,
+// and gives up with None when a step finds nothing to work with.
+// Every step taken is described in `log`.
+fn process_note(note: &DisplayNote, log: &mut Vec<String>) -> Option<HashMap<char, char>> {
     use crate::seq_ops::IterOps;
     use display_segment::{A, B, C, COUNT, D, E, F, G};
 
-    fn find_by_length(digits: &[String], length: usize) -> Vec<usize> {
-        digits
-            .iter()
-            .enumerate()
-            .filter(|(_, x)| x.len() == length)
-            .map(|(i, _)| i)
-            .collect()
+    let signals = &note.input;
+    let mut digit_to_signal = [0usize; 10];
+    let mut sig_to_dis = [' '; COUNT];
+
+    digit_to_signal[1] = one_by_length(signals, 2)?;
+    digit_to_signal[4] = one_by_length(signals, 4)?;
+    digit_to_signal[7] = one_by_length(signals, 3)?;
+    digit_to_signal[8] = one_by_length(signals, 7)?;
+
+    log.push(format!(
+        "unique lengths: 1 = {}, 4 = {}, 7 = {}, 8 = {}",
+        signals[digit_to_signal[1]],
+        signals[digit_to_signal[4]],
+        signals[digit_to_signal[7]],
+        signals[digit_to_signal[8]]
+    ));
+
+    let mut sixes = find_by_length(signals, 6); // 0, 6, 9
+    let mut six_to_remove = 0;
+    for &i in sixes.iter() {
+        let current = &signals[i];
+        let intersection = current
+            .chars()
+            .intersect(signals[digit_to_signal[1]].chars());
+        if intersection.len() == 1 {
+            digit_to_signal[6] = i;
+            let f = intersection[0];
+            sig_to_dis[F] = f;
+            sig_to_dis[C] = signals[digit_to_signal[1]].chars().find(|c| *c != f)?;
+            log.push(format!(
+                "6 = {}, the six-segment pattern sharing one wire with 1: F = {}, C = {}",
+                current, sig_to_dis[F], sig_to_dis[C]
+            ));
+            break;
+        }
+        six_to_remove += 1;
+    }
+
+    if six_to_remove == sixes.len() {
+        return None;
+    }
+    sixes.remove(six_to_remove); // 0, 9
+    six_to_remove = 0;
+    for &i in sixes.iter() {
+        let current = &signals[i];
+        let intersection = current
+            .chars()
+            .intersect(signals[digit_to_signal[4]].chars());
+        if intersection.len() == 4 {
+            digit_to_signal[9] = i;
+            log.push(format!(
+                "9 = {}, the six-segment pattern containing all of 4",
+                current
+            ));
+            break;
+        }
+        six_to_remove += 1;
     }
 
-    fn one_by_length(digits: &[String], length: usize) -> usize {
-        find_by_length(digits, length)[0]
+    if six_to_remove == sixes.len() {
+        return None;
     }
+    sixes.remove(six_to_remove); // 0
+    digit_to_signal[0] = *sixes.first()?;
+    log.push(format!(
+        "0 = {}, the six-segment pattern left over",
+        signals[digit_to_signal[0]]
+    ));
+
+    // --- now we know 0, 1, 4, 6, 7, 8, 9 ---
+    // --- let's work on letter mappings   ---
+    let a = signals[digit_to_signal[7]]
+        .chars()
+        .difference(signals[digit_to_signal[4]].chars());
+    sig_to_dis[A] = *a.first()?;
+    log.push(format!("A = 7 - 4 = {}", sig_to_dis[A]));
+
+    let e = signals[digit_to_signal[8]]
+        .chars()
+        .difference(signals[digit_to_signal[9]].chars());
+    sig_to_dis[E] = *e.first()?;
+    log.push(format!("E = 8 - 9 = {}", sig_to_dis[E]));
+
+    let d = signals[digit_to_signal[8]]
+        .chars()
+        .difference(signals[digit_to_signal[0]].chars());
+    sig_to_dis[D] = *d.first()?;
+    log.push(format!("D = 8 - 0 = {}", sig_to_dis[D]));
+
+    let b = signals[digit_to_signal[4]].chars().difference(
+        vec![sig_to_dis[C], sig_to_dis[D], sig_to_dis[F]]
+            .iter()
+            .collect::<String>()
+            .chars(),
+    );
+    sig_to_dis[B] = *b.first()?;
+    log.push(format!("B = 4 - (C, D, F) = {}", sig_to_dis[B]));
+
+    let g = signals[digit_to_signal[8]].chars().difference(
+        vec![
+            sig_to_dis[A],
+            sig_to_dis[B],
+            sig_to_dis[C],
+            sig_to_dis[D],
+            sig_to_dis[E],
+            sig_to_dis[F],
+        ]
+        .iter()
+        .collect::<String>()
+        .chars(),
+    );
+    sig_to_dis[G] = *g.first()?;
+    log.push(format!("G = 8 - (A, B, C, D, E, F) = {}", sig_to_dis[G]));
+    Some(
+        sig_to_dis
+            .into_iter()
+            .zip('a'..='g')
+            .collect::<HashMap<char, char>>(),
+    )
+}
 
-    fn process_note(note: &DisplayNote) -> HashMap<char, char> {
-        let signals = &note.input;
-        let mut digit_to_signal = [0usize; 10];
-        let mut sig_to_dis = [' '; COUNT];
-
-        digit_to_signal[1] = one_by_length(signals, 2);
-        digit_to_signal[4] = one_by_length(signals, 4);
-        digit_to_signal[7] = one_by_length(signals, 3);
-        digit_to_signal[8] = one_by_length(signals, 7);
-
-        //println!("unique digits: {:?}", digit_to_signal);
-
-        let mut sixes = find_by_length(signals, 6); // 0, 6, 9
-        let mut six_to_remove = 0;
-        for &i in sixes.iter() {
-            let current = &signals[i];
-            let intersection = current
-                .chars()
-                .intersect(signals[digit_to_signal[1]].chars());
-            if intersection.len() == 1 {
-                digit_to_signal[6] = i;
-                let f = intersection[0];
-                sig_to_dis[F] = f;
-                sig_to_dis[C] = signals[digit_to_signal[1]]
-                    .chars()
-                    .find(|c| *c != f)
-                    .unwrap();
-                break;
+fn translate(word: &str, dictionary: &HashMap<char, char>) -> String {
+    word.chars().map(|c| dictionary[&c]).collect()
+}
+
+// A display font: how many segments there are and which segments light up
+// for each glyph. Definition files have `segments: N` and `label: segments`
+// lines, with segments named from `a` and `#` starting a comment.
+#[derive(Clone, Debug)]
+struct Font {
+    pub segments: usize,
+    pub glyphs: Vec<(String, u32)>,
+}
+
+impl FromStr for Font {
+    type Err = Boom;
+
+    fn from_str(s: &str) -> Result<Font, Boom> {
+        let mut segments = None;
+        let mut glyphs = vec![];
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| Boom::from_display(format!("cannot read font line {}", line)))?;
+            let (key, value) = (key.trim(), value.trim());
+            match (key, segments) {
+                ("segments", _) => {
+                    // checked right away, masks are built from it line by line
+                    let count = value.parse::<usize>()?;
+                    if !(1..=26).contains(&count) {
+                        return Err(Boom::from_display(format!(
+                            "fonts need between 1 and 26 segments, not {}",
+                            count
+                        )));
+                    }
+                    segments = Some(count);
+                }
+                (_, Some(count)) => glyphs.push((key.to_string(), segment_mask(value, count)?)),
+                (_, None) => {
+                    return Err(Boom::from_display("font must give segments before glyphs"))
+                }
             }
-            six_to_remove += 1;
         }
 
-        sixes.remove(six_to_remove); // 0, 9
-        six_to_remove = 0;
-        for &i in sixes.iter() {
-            let current = &signals[i];
-            let intersection = current
-                .chars()
-                .intersect(signals[digit_to_signal[4]].chars());
-            if intersection.len() == 4 {
-                digit_to_signal[9] = i;
-                break;
+        let font = Font {
+            segments: segments.ok_or_else(|| Boom::from_display("font has no segment count"))?,
+            glyphs: glyphs,
+        };
+        for (i, (label, mask)) in font.glyphs.iter().enumerate() {
+            if let Some((other, _)) = font.glyphs[..i].iter().find(|(_, m)| m == mask) {
+                return Err(Boom::from_display(format!(
+                    "glyphs {} and {} light the same segments",
+                    other, label
+                )));
             }
-            six_to_remove += 1;
         }
+        Ok(font)
+    }
+}
 
-        sixes.remove(six_to_remove); // 0
-        digit_to_signal[0] = sixes[0];
+impl Font {
+    pub fn load(path: &str) -> BResult<Font> {
+        Ok(util::read_file(path)?.parse::<Font>()?)
+    }
 
-        // --- now we know 0, 1, 4, 6, 7, 8, 9 ---
-        // --- let's work on letter mappings   ---
-        let a = signals[digit_to_signal[7]]
-            .chars()
-            .difference(signals[digit_to_signal[4]].chars());
-        sig_to_dis[A] = a[0];
+    pub fn masks(&self) -> Vec<u32> {
+        self.glyphs.iter().map(|(_, m)| *m).collect()
+    }
 
-        let e = signals[digit_to_signal[8]]
-            .chars()
-            .difference(signals[digit_to_signal[9]].chars());
-        sig_to_dis[E] = e[0];
+    pub fn read_mask(&self, mask: u32) -> Option<&str> {
+        self.glyphs
+            .iter()
+            .find(|(_, m)| *m == mask)
+            .map(|(label, _)| label.as_str())
+    }
 
-        let d = signals[digit_to_signal[8]]
-            .chars()
-            .difference(signals[digit_to_signal[0]].chars());
-        sig_to_dis[D] = d[0];
+    pub fn read(&self, segments: &str) -> Option<&str> {
+        self.read_mask(segment_mask(segments, self.segments).ok()?)
+    }
+}
 
-        let b = signals[digit_to_signal[4]].chars().difference(
-            vec![sig_to_dis[C], sig_to_dis[D], sig_to_dis[F]]
-                .iter()
-                .collect::<String>()
-                .chars(),
-        );
-        sig_to_dis[B] = b[0];
-
-        let g = signals[digit_to_signal[8]].chars().difference(
-            vec![
-                sig_to_dis[A],
-                sig_to_dis[B],
-                sig_to_dis[C],
-                sig_to_dis[D],
-                sig_to_dis[E],
-                sig_to_dis[F],
-            ]
+// Wire and segment sets as bitmasks, bit 0 standing for `a`.
+fn segment_mask(word: &str, count: usize) -> Result<u32, Boom> {
+    let mut mask = 0u32;
+    for c in word.chars() {
+        let bit = (c as u32).wrapping_sub('a' as u32);
+        if bit as usize >= count {
+            return Err(Boom::from_display(format!(
+                "unexpected wire {} in pattern {}",
+                c, word
+            )));
+        }
+        mask |= 1 << bit;
+    }
+    Ok(mask)
+}
+
+// wiring[wire] is the segment lit by that wire
+type Wiring = Vec<usize>;
+
+fn map_mask(mask: u32, wiring: &Wiring) -> u32 {
+    wiring
+        .iter()
+        .enumerate()
+        .filter(|(wire, _)| mask & (1 << wire) != 0)
+        .fold(0, |acc, (_, segment)| acc | (1 << segment))
+}
+
+// Treats the wiring as a permutation of `count` wires onto segments: every
+// observed pattern has to light up exactly one of the glyphs. Candidates are
+// narrowed by pattern length first, then the rest is backtracking.
+fn solve_wirings(patterns: &[u32], glyphs: &[u32], count: usize) -> Vec<Wiring> {
+    let full = (1u32 << count) - 1;
+    let mut candidates = vec![full; count];
+
+    for p in patterns {
+        let options = glyphs
             .iter()
-            .collect::<String>()
-            .chars(),
-        );
-        sig_to_dis[G] = g[0];
+            .filter(|g| g.count_ones() == p.count_ones())
+            .collect::<Vec<_>>();
+        if options.len() == 0 {
+            return vec![];
+        }
+        let union = options.iter().fold(0, |acc, g| acc | **g);
+        let common = options.iter().fold(full, |acc, g| acc & **g);
+        for (wire, c) in candidates.iter_mut().enumerate() {
+            if p & (1 << wire) != 0 {
+                *c &= union;
+            } else {
+                *c &= !common;
+            }
+        }
+    }
 
-        //println!("story so far: {:?}", (A..=G).zip(sig_to_dis.iter()).collect::<Vec<_>>());
-        sig_to_dis
-            .into_iter()
-            .zip('a'..='g')
-            .collect::<HashMap<char, char>>()
+    // a wire with a single candidate takes that segment away from the rest
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for wire in 0..count {
+            let c = candidates[wire];
+            if c.count_ones() != 1 {
+                continue;
+            }
+            for (other, oc) in candidates.iter_mut().enumerate() {
+                if other != wire && *oc & c != 0 {
+                    *oc &= !c;
+                    changed = true;
+                }
+            }
+        }
+    }
+    if candidates.iter().any(|c| *c == 0) {
+        return vec![];
     }
 
-    fn translate(word: &str, dictionary: &HashMap<char, char>) -> String {
-        word.chars().map(|c| dictionary[&c]).collect()
+    // every pattern must still be able to become some glyph, given the wires
+    // assigned so far
+    fn fits(patterns: &[u32], glyphs: &[u32], wiring: &[usize]) -> bool {
+        let assigned = (1u32 << wiring.len()) - 1;
+        patterns.iter().all(|p| {
+            let mut lit = 0u32;
+            let mut dark = 0u32;
+            for (wire, segment) in wiring.iter().enumerate() {
+                if p & (1 << wire) != 0 {
+                    lit |= 1 << segment;
+                } else if assigned & (1 << wire) != 0 {
+                    dark |= 1 << segment;
+                }
+            }
+            glyphs
+                .iter()
+                .any(|g| g.count_ones() == p.count_ones() && g & lit == lit && g & dark == 0)
+        })
     }
 
-    fn display_to_digit(word: &str) -> Option<i32> {
-        let mut letters = word.chars().collect::<Vec<_>>();
-        letters.sort();
-        let sorted_word: String = letters.into_iter().collect::<String>();
-        match sorted_word.as_str() {
-            "abcefg" => Some(0),
-            "cf" => Some(1),
-            "acdeg" => Some(2),
-            "acdfg" => Some(3),
-            "bcdf" => Some(4),
-            "abdfg" => Some(5),
-            "abdefg" => Some(6),
-            "acf" => Some(7),
-            "abcdefg" => Some(8),
-            "abcdfg" => Some(9),
-            _ => None,
+    fn search(
+        patterns: &[u32],
+        glyphs: &[u32],
+        candidates: &[u32],
+        wiring: &mut Vec<usize>,
+        used: u32,
+        result: &mut Vec<Wiring>,
+    ) {
+        let wire = wiring.len();
+        if wire == candidates.len() {
+            result.push(wiring.clone());
+            return;
+        }
+        for segment in 0..candidates.len() {
+            let bit = 1 << segment;
+            if candidates[wire] & bit == 0 || used & bit != 0 {
+                continue;
+            }
+            wiring.push(segment);
+            if fits(patterns, glyphs, wiring) {
+                search(patterns, glyphs, candidates, wiring, used | bit, result);
+            }
+            wiring.pop();
         }
     }
 
+    let mut result = vec![];
+    search(patterns, glyphs, &candidates, &mut vec![], 0, &mut result);
+    result
+}
+
+#[derive(Debug)]
+enum Decoding {
+    Unique(String),
+    // several wirings fit; `values` lists every reading they give
+    Ambiguous { wirings: usize, values: Vec<String> },
+    Inconsistent,
+}
+
+fn decode_note(note: &DisplayNote, font: &Font) -> Result<Decoding, Boom> {
+    let count = font.segments;
+    let glyphs = font.masks();
+    let outputs = note
+        .output
+        .iter()
+        .map(|w| segment_mask(w, count))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut patterns = note
+        .input
+        .iter()
+        .map(|w| segment_mask(w, count))
+        .collect::<Result<Vec<_>, _>>()?;
+    patterns.extend(outputs.iter());
+
+    let wirings = solve_wirings(&patterns, &glyphs, count);
+
+    let mut values = wirings
+        .iter()
+        .map(|wiring| {
+            outputs
+                .iter()
+                .map(|o| font.read_mask(map_mask(*o, wiring)).unwrap())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>();
+    values.sort();
+    values.dedup();
+
+    Ok(match wirings.len() {
+        0 => Decoding::Inconsistent,
+        1 => Decoding::Unique(values.remove(0)),
+        n => Decoding::Ambiguous {
+            wirings: n,
+            values: values,
+        },
+    })
+}
+
+fn part2(notes: &Input, font: &Font) {
     let mut sum = 0;
-    for note in notes.iter() {
-        let signal_to_display = process_note(note);
-        dbg!(&signal_to_display);
-
-        let mut num = 0;
-        for o in &note.output {
-            let translated = translate(&o, &signal_to_display);
-            let digit = display_to_digit(&translated).unwrap();
-            num = num * 10 + digit;
+    let mut add = |i: usize, reading: &str| match reading.parse::<i32>() {
+        Ok(num) => sum += num,
+        Err(_) => println!("note {}: {} is not a number", i, reading),
+    };
+
+    for (i, note) in notes.iter().enumerate() {
+        match decode_note(note, font) {
+            Ok(Decoding::Unique(reading)) => add(i, &reading),
+            Ok(Decoding::Ambiguous { wirings, values }) if values.len() == 1 => {
+                println!(
+                    "note {}: {} wirings fit, all reading {}",
+                    i, wirings, values[0]
+                );
+                add(i, &values[0]);
+            }
+            Ok(Decoding::Ambiguous { wirings, values }) => {
+                println!(
+                    "note {}: {} wirings fit, reading any of {:?}",
+                    i, wirings, values
+                )
+            }
+            Ok(Decoding::Inconsistent) => println!("note {}: no wiring explains it", i),
+            Err(e) => println!("note {}: {}", i, e),
         }
-        sum += num;
     }
 
     println!("part 2 solution: {}", sum);
 }
 
-fn sized_string<const N: usize>(s: &str) -> [String; N] {
-    s.trim()
-        .split(" ")
-        .map(String::from)
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap()
+// cross-checks the constraint solver against the hand-derived deduction
+fn part3(notes: &Input, font: &Font) {
+    let explain = util::has_flag("explain");
+
+    let mut agreed = 0;
+    for (i, note) in notes.iter().enumerate() {
+        if note.input.len() != 10 || font.segments != display_segment::COUNT {
+            continue;
+        }
+        // the deduction only makes sense for notes the solver can read
+        let solved = match decode_note(note, font) {
+            Ok(Decoding::Unique(value)) => value,
+            other => {
+                println!("note {}: solver {:?}, skipping deduction", i, other.ok());
+                continue;
+            }
+        };
+
+        let mut log = vec![];
+        let signal_to_display = match process_note(note, &mut log) {
+            Some(map) => map,
+            None => {
+                println!("note {}: deduction got stuck after {:?}", i, log);
+                continue;
+            }
+        };
+        let translated = note
+            .output
+            .iter()
+            .map(|o| translate(o, &signal_to_display))
+            .collect::<Vec<_>>();
+        let deduced = translated
+            .iter()
+            .map(|t| font.read(t))
+            .collect::<Option<String>>();
+
+        if explain {
+            println!("note {}: {}", i, note.output.join(" "));
+            for step in log.iter() {
+                println!("  {}", step);
+            }
+            print!("{}", render_display(&translated));
+        }
+
+        match deduced {
+            Some(value) if value == solved => agreed += 1,
+            other => println!("note {}: deduction {:?}, solver {}", i, other, solved),
+        }
+    }
+
+    println!("deduction and solver agree on {} notes", agreed);
+}
+
+// Scrambles the wires of every font under input/fonts, shows all of its
+// glyphs once plus a few more, and checks the solver reads them back.
+fn part4() -> BResult<()> {
+    let mut paths = std::fs::read_dir("input/fonts")?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    let mut rng = Rng::from_seed(8);
+    for path in paths {
+        let font = Font::load(&path.to_string_lossy())?;
+
+        // segment_to_wire[segment] is the wire that drives it
+        let mut segment_to_wire = (0..font.segments).collect::<Vec<_>>();
+        rng.shuffle(&mut segment_to_wire);
+        let encode = |mask: u32| {
+            let mut wires = (0..font.segments)
+                .filter(|seg| mask & (1 << seg) != 0)
+                .map(|seg| (b'a' + segment_to_wire[seg] as u8) as char)
+                .collect::<Vec<_>>();
+            wires.sort();
+            wires.into_iter().collect::<String>()
+        };
+
+        let mut input = font
+            .glyphs
+            .iter()
+            .map(|(_, m)| encode(*m))
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut input);
+        let shown = (0..4)
+            .map(|_| &font.glyphs[rng.below(font.glyphs.len())])
+            .collect::<Vec<_>>();
+        let note = DisplayNote {
+            input: input,
+            output: shown.iter().map(|(_, m)| encode(*m)).collect(),
+        };
+        let expected = shown.iter().map(|(l, _)| l.as_str()).collect::<String>();
+
+        println!(
+            "{}: expected {}, decoded {:?}",
+            path.display(),
+            expected,
+            decode_note(&note, &font)?
+        );
+    }
+
+    Ok(())
+}
+
+// Draws lit segments as their letter and dark ones as dots, the same way as
+// the diagram at the top of this file.
+fn render_display(digits: &[String]) -> String {
+    let lit = |digit: &String, segment: char, width: usize| {
+        let c = if digit.contains(segment) {
+            segment
+        } else {
+            '.'
+        };
+        std::iter::repeat(c).take(width).collect::<String>()
+    };
+    let across = |segment: char| {
+        digits
+            .iter()
+            .map(|d| format!(" {}  ", lit(d, segment, 4)))
+            .collect::<String>()
+    };
+    let down = |left: char, right: char| {
+        digits
+            .iter()
+            .map(|d| format!("{}    {}  ", lit(d, left, 1), lit(d, right, 1)))
+            .collect::<String>()
+    };
+
+    let rows = [
+        across('a'),
+        down('b', 'c'),
+        down('b', 'c'),
+        across('d'),
+        down('e', 'f'),
+        down('e', 'f'),
+        across('g'),
+    ];
+    rows.iter().map(|r| format!("{}\n", r.trim_end())).collect()
+}
+
+fn words(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
 }
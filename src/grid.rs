@@ -131,3 +131,90 @@ where
         Ok(Grid::from_rows(util::parse_lines::<Row<T>>(text)?))
     }
 }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineKind {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    Sloped,
+}
+
+pub fn line_kind(Point(x1, y1): Point, Point(x2, y2): Point) -> LineKind {
+    let dx = (x2 as i64 - x1 as i64).abs();
+    let dy = (y2 as i64 - y1 as i64).abs();
+    if dy == 0 {
+        LineKind::Horizontal
+    } else if dx == 0 {
+        LineKind::Vertical
+    } else if dx == dy {
+        LineKind::Diagonal
+    } else {
+        LineKind::Sloped
+    }
+}
+
+// Every lattice point lying exactly on the segment, from start to end.
+// Steps are (dx, dy) divided by their gcd, so any slope works.
+#[derive(Clone, Debug)]
+pub struct LinePoints {
+    x: i64,
+    y: i64,
+    step_x: i64,
+    step_y: i64,
+    remaining: usize,
+}
+
+impl Iterator for LinePoints {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let point = Point(self.x as usize, self.y as usize);
+        self.x += self.step_x;
+        self.y += self.step_y;
+        self.remaining -= 1;
+        Some(point)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub fn rasterize(from: Point, to: Point) -> (LineKind, LinePoints) {
+    fn gcd(a: i64, b: i64) -> i64 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    let (Point(x1, y1), Point(x2, y2)) = (from, to);
+    let dx = x2 as i64 - x1 as i64;
+    let dy = y2 as i64 - y1 as i64;
+    let steps = gcd(dx.abs(), dy.abs());
+
+    let points = if steps == 0 {
+        LinePoints {
+            x: x1 as i64,
+            y: y1 as i64,
+            step_x: 0,
+            step_y: 0,
+            remaining: 1,
+        }
+    } else {
+        LinePoints {
+            x: x1 as i64,
+            y: y1 as i64,
+            step_x: dx / steps,
+            step_y: dy / steps,
+            remaining: steps as usize + 1,
+        }
+    };
+
+    (line_kind(from, to), points)
+}
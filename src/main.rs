@@ -1,5 +1,5 @@
-#![feature(array_windows)]
-
+mod bitmatrix;
+mod brackets;
 mod day1;
 mod day10;
 mod day11;
@@ -16,8 +16,13 @@ mod day7;
 mod day8;
 mod day9;
 mod grid;
+mod matrix;
+mod optimize;
+mod rng;
 mod seq_ops;
+mod svg;
 mod util;
+mod window;
 
 use util::BResult;
 
@@ -0,0 +1,145 @@
+// Integer matrices for stepping linear recurrences:
+// the state after n steps is `transition.pow(n) * initial`.
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    values: Vec<u64>,
+}
+
+impl Matrix {
+    pub fn zero(rows: usize, cols: usize) -> Matrix {
+        Matrix {
+            rows: rows,
+            cols: cols,
+            values: vec![0; rows * cols],
+        }
+    }
+
+    pub fn identity(size: usize) -> Matrix {
+        let mut m = Matrix::zero(size, size);
+        for i in 0..size {
+            m.set(i, i, 1);
+        }
+        m
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u64 {
+        assert!(row < self.rows, "row out of range");
+        assert!(col < self.cols, "col out of range");
+
+        self.values[row * self.cols + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, val: u64) {
+        assert!(row < self.rows, "row out of range");
+        assert!(col < self.cols, "col out of range");
+
+        self.values[row * self.cols + col] = val
+    }
+
+    pub fn add_at(&mut self, row: usize, col: usize, val: u64) {
+        let current = self.get(row, col);
+        self.set(row, col, current + val)
+    }
+
+    pub fn checked_mul(&self, other: &Matrix) -> Option<Matrix> {
+        assert!(self.cols == other.rows, "matrix dimensions do not match");
+
+        let mut result = Matrix::zero(self.rows, other.cols);
+        for r in 0..self.rows {
+            for k in 0..self.cols {
+                let a = self.get(r, k);
+                if a == 0 {
+                    continue;
+                }
+                for c in 0..other.cols {
+                    let product = a.checked_mul(other.get(k, c))?;
+                    let sum = result.get(r, c).checked_add(product)?;
+                    result.set(r, c, sum);
+                }
+            }
+        }
+        Some(result)
+    }
+
+    pub fn mul_mod(&self, other: &Matrix, modulus: u64) -> Matrix {
+        assert!(self.cols == other.rows, "matrix dimensions do not match");
+        assert!(modulus > 0, "modulus must be positive");
+
+        let m = modulus as u128;
+        let mut result = Matrix::zero(self.rows, other.cols);
+        for r in 0..self.rows {
+            for c in 0..other.cols {
+                let mut sum = 0u128;
+                for k in 0..self.cols {
+                    sum = (sum + self.get(r, k) as u128 * other.get(k, c) as u128) % m;
+                }
+                result.set(r, c, sum as u64);
+            }
+        }
+        result
+    }
+
+    // exponentiation by squaring, None as soon as any entry overflows
+    pub fn checked_pow(&self, exp: u64) -> Option<Matrix> {
+        assert!(self.rows == self.cols, "only square matrices have powers");
+
+        let mut result = Matrix::identity(self.rows);
+        let mut base = self.clone();
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.checked_mul(&base)?;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+        Some(result)
+    }
+
+    pub fn pow_mod(&self, exp: u64, modulus: u64) -> Matrix {
+        assert!(self.rows == self.cols, "only square matrices have powers");
+
+        let mut result = Matrix::identity(self.rows).reduce(modulus);
+        let mut base = self.reduce(modulus);
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul_mod(&base, modulus);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.mul_mod(&base, modulus);
+            }
+        }
+        result
+    }
+
+    pub fn checked_apply(&self, vector: &[u64]) -> Option<Vec<u64>> {
+        self.checked_mul(&Matrix::column(vector)).map(|m| m.values)
+    }
+
+    pub fn apply_mod(&self, vector: &[u64], modulus: u64) -> Vec<u64> {
+        self.mul_mod(&Matrix::column(vector), modulus).values
+    }
+
+    fn reduce(&self, modulus: u64) -> Matrix {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            values: self.values.iter().map(|v| v % modulus).collect(),
+        }
+    }
+
+    fn column(vector: &[u64]) -> Matrix {
+        Matrix {
+            rows: vector.len(),
+            cols: 1,
+            values: vector.to_vec(),
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use crate::util::{self, Boom};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Alignment {
+    pub position: i32,
+    pub cost: i64,
+}
+
+pub fn total_cost(positions: &[i32], target: i32, distance_fn: impl Fn(i32, i32) -> i32) -> i64 {
+    positions
+        .iter()
+        .map(|x| distance_fn(*x, target) as i64)
+        .sum()
+}
+
+// sum of |x - target| is minimized by the median
+pub fn linear_optimum(positions: &[i32]) -> Alignment {
+    let median = util::median(&mut positions.to_vec());
+    Alignment {
+        position: median,
+        cost: total_cost(positions, median, |x, t| (x - t).abs()),
+    }
+}
+
+// triangular cost d(d+1)/2 is minimized within half a step of the mean,
+// so only the integers around it need checking
+pub fn triangular_optimum(positions: &[i32]) -> Alignment {
+    fn triangular(x: i32, target: i32) -> i32 {
+        let abs = (x - target).abs();
+        abs * (abs + 1) / 2
+    }
+
+    let sum: i64 = positions.iter().map(|x| *x as i64).sum();
+    let mean = sum.div_euclid(positions.len() as i64) as i32;
+
+    ((mean - 1)..=(mean + 2))
+        .map(|target| Alignment {
+            position: target,
+            cost: total_cost(positions, target, triangular),
+        })
+        .min_by_key(|a| (a.cost, a.position))
+        .unwrap()
+}
+
+// ternary search over the input range, valid for any distance_fn that is
+// convex in the target
+pub fn convex_optimum(positions: &[i32], distance_fn: impl Fn(i32, i32) -> i32) -> Alignment {
+    let cost = |target: i32| total_cost(positions, target, &distance_fn);
+
+    let mut lo = *positions.iter().min().unwrap();
+    let mut hi = *positions.iter().max().unwrap();
+    while hi - lo > 2 {
+        let m1 = lo + (hi - lo) / 3;
+        let m2 = hi - (hi - lo) / 3;
+        let (c1, c2) = (cost(m1), cost(m2));
+        if c1 < c2 {
+            hi = m2 - 1;
+        } else if c1 > c2 {
+            lo = m1 + 1;
+        } else {
+            lo = m1;
+            hi = m2;
+        }
+    }
+
+    (lo..=hi)
+        .map(|target| Alignment {
+            position: target,
+            cost: cost(target),
+        })
+        .min_by_key(|a| (a.cost, a.position))
+        .unwrap()
+}
+
+// A crab somewhere in D dimensions. An AxisCostFn is charged on each axis
+// the crab moves along, and the sum is multiplied by the crab's weight.
+#[derive(Clone, Copy, Debug)]
+pub struct Crab<const D: usize> {
+    pub position: [i32; D],
+    pub weight: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultiAlignment<const D: usize> {
+    pub position: [i32; D],
+    pub cost: i64,
+    pub crab_costs: Vec<i64>,
+}
+
+// The cost of moving `distance` along a single axis. In more than one
+// dimension it's charged once per axis: a cap limits each axis on its own,
+// and a fixed charge for moving at all is paid on every axis that moves.
+pub enum AxisCostFn {
+    Linear,
+    Triangular,
+    Quadratic,
+    // at most the cap per axis, not per crab
+    Capped(Box<AxisCostFn>, i64),
+    Expr(CostExpr),
+    Closure(Box<dyn Fn(i64) -> i64>),
+}
+
+impl AxisCostFn {
+    pub fn axis_cost(&self, distance: i64) -> i64 {
+        match self {
+            AxisCostFn::Linear => distance,
+            AxisCostFn::Triangular => distance * (distance + 1) / 2,
+            AxisCostFn::Quadratic => distance * distance,
+            AxisCostFn::Capped(inner, cap) => std::cmp::min(inner.axis_cost(distance), *cap),
+            AxisCostFn::Expr(expr) => expr.eval(distance),
+            AxisCostFn::Closure(f) => f(distance),
+        }
+    }
+}
+
+impl std::fmt::Debug for AxisCostFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AxisCostFn::Linear => write!(f, "Linear"),
+            AxisCostFn::Triangular => write!(f, "Triangular"),
+            AxisCostFn::Quadratic => write!(f, "Quadratic"),
+            AxisCostFn::Capped(inner, cap) => write!(f, "Capped({:?}, {} per axis)", inner, cap),
+            AxisCostFn::Expr(expr) => write!(f, "Expr({:?})", expr),
+            AxisCostFn::Closure(_) => write!(f, "Closure"),
+        }
+    }
+}
+
+impl FromStr for AxisCostFn {
+    type Err = Boom;
+
+    fn from_str(s: &str) -> Result<AxisCostFn, Boom> {
+        match s.trim() {
+            "linear" => Ok(AxisCostFn::Linear),
+            "triangular" => Ok(AxisCostFn::Triangular),
+            "quadratic" => Ok(AxisCostFn::Quadratic),
+            expr => Ok(AxisCostFn::Expr(expr.parse::<CostExpr>()?)),
+        }
+    }
+}
+
+// Arithmetic over the distance `d`: numbers, + - * /, parentheses,
+// unary minus and the functions min, max and abs.
+#[derive(Clone, Debug)]
+pub enum CostExpr {
+    Distance,
+    Number(i64),
+    Neg(Box<CostExpr>),
+    Binary(char, Box<CostExpr>, Box<CostExpr>),
+    Call(String, Vec<CostExpr>),
+}
+
+impl CostExpr {
+    // Arithmetic saturates, and dividing by zero counts as infinitely
+    // expensive (i64::MAX), so any expression can be evaluated anywhere.
+    pub fn eval(&self, d: i64) -> i64 {
+        match self {
+            CostExpr::Distance => d,
+            CostExpr::Number(n) => *n,
+            CostExpr::Neg(e) => e.eval(d).saturating_neg(),
+            CostExpr::Binary(op, l, r) => {
+                let (l, r) = (l.eval(d), r.eval(d));
+                match op {
+                    '+' => l.saturating_add(r),
+                    '-' => l.saturating_sub(r),
+                    '*' => l.saturating_mul(r),
+                    _ if r == 0 => i64::MAX,
+                    _ => l.saturating_div(r),
+                }
+            }
+            CostExpr::Call(name, args) => {
+                let values = args.iter().map(|a| a.eval(d)).collect::<Vec<_>>();
+                match name.as_str() {
+                    "min" => *values.iter().min().unwrap(),
+                    "max" => *values.iter().max().unwrap(),
+                    _ => values[0].saturating_abs(),
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for CostExpr {
+    type Err = Boom;
+
+    fn from_str(s: &str) -> Result<CostExpr, Boom> {
+        let tokens = tokenize(s)?;
+        let mut pos = 0;
+        let expr = parse_sum(&tokens, &mut pos)?;
+        if pos < tokens.len() {
+            return Err(Boom::from_display(format!(
+                "unexpected {:?} in cost expression {}",
+                tokens[pos], s
+            )));
+        }
+        Ok(expr)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Symbol(char),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, Boom> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            tokens.push(Token::Number(text.parse::<i64>()?));
+        } else if c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(Boom::from_display(format!(
+                "unexpected character {} in cost expression",
+                c
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+fn expect(tokens: &[Token], pos: &mut usize, symbol: char) -> Result<(), Boom> {
+    match tokens.get(*pos) {
+        Some(Token::Symbol(c)) if *c == symbol => {
+            *pos += 1;
+            Ok(())
+        }
+        other => Err(Boom::from_display(format!(
+            "expected {} in cost expression, found {:?}",
+            symbol, other
+        ))),
+    }
+}
+
+fn parse_sum(tokens: &[Token], pos: &mut usize) -> Result<CostExpr, Boom> {
+    let mut expr = parse_product(tokens, pos)?;
+    while let Some(Token::Symbol(op @ ('+' | '-'))) = tokens.get(*pos) {
+        *pos += 1;
+        let right = parse_product(tokens, pos)?;
+        expr = CostExpr::Binary(*op, Box::new(expr), Box::new(right));
+    }
+    Ok(expr)
+}
+
+fn parse_product(tokens: &[Token], pos: &mut usize) -> Result<CostExpr, Boom> {
+    let mut expr = parse_factor(tokens, pos)?;
+    while let Some(Token::Symbol(op @ ('*' | '/'))) = tokens.get(*pos) {
+        *pos += 1;
+        let right = parse_factor(tokens, pos)?;
+        expr = CostExpr::Binary(*op, Box::new(expr), Box::new(right));
+    }
+    Ok(expr)
+}
+
+fn parse_factor(tokens: &[Token], pos: &mut usize) -> Result<CostExpr, Boom> {
+    let token = tokens
+        .get(*pos)
+        .ok_or_else(|| Boom::from_display("cost expression ended unexpectedly"))?;
+    *pos += 1;
+
+    match token {
+        Token::Number(n) => Ok(CostExpr::Number(*n)),
+        Token::Symbol('-') => Ok(CostExpr::Neg(Box::new(parse_factor(tokens, pos)?))),
+        Token::Symbol('(') => {
+            let expr = parse_sum(tokens, pos)?;
+            expect(tokens, pos, ')')?;
+            Ok(expr)
+        }
+        Token::Ident(name) if name == "d" => Ok(CostExpr::Distance),
+        Token::Ident(name) if ["min", "max", "abs"].contains(&name.as_str()) => {
+            expect(tokens, pos, '(')?;
+            let mut args = vec![parse_sum(tokens, pos)?];
+            while tokens.get(*pos) == Some(&Token::Symbol(',')) {
+                *pos += 1;
+                args.push(parse_sum(tokens, pos)?);
+            }
+            expect(tokens, pos, ')')?;
+            if name == "abs" && args.len() != 1 {
+                return Err(Boom::from_display("abs takes exactly one argument"));
+            }
+            Ok(CostExpr::Call(name.clone(), args))
+        }
+        other => Err(Boom::from_display(format!(
+            "unexpected {:?} in cost expression",
+            other
+        ))),
+    }
+}
+
+// Brute force over every target between the outermost positions, with each
+// position's distance scaled by its weight. None without any positions.
+pub fn generic_min_distance_value(
+    positions: &[i32],
+    weights: &[i64],
+    distance_fn: impl Fn(i32, i32) -> i64,
+) -> Option<(i32, i64)> {
+    let min = *positions.iter().min()?;
+    let max = *positions.iter().max()?;
+
+    (min..=max)
+        .map(|target| {
+            let cost = positions
+                .iter()
+                .zip(weights)
+                .map(|(x, w)| w.saturating_mul(distance_fn(*x, target)))
+                .fold(0i64, |acc, c| acc.saturating_add(c));
+            (target, cost)
+        })
+        .min_by_key(|(target, cost)| (*cost, *target))
+}
+
+pub fn weighted_median(positions: &[i32], weights: &[i64]) -> Option<i32> {
+    if positions.is_empty() {
+        return None;
+    }
+    if weights.iter().all(|w| *w == 1) {
+        return Some(util::median(&mut positions.to_vec()));
+    }
+
+    let mut pairs = positions.iter().zip(weights).collect::<Vec<_>>();
+    pairs.sort();
+    let total: i64 = weights.iter().sum();
+    let mut running = 0;
+    for (x, w) in pairs {
+        running += w;
+        if running * 2 >= total {
+            return Some(*x);
+        }
+    }
+    None
+}
+
+// Costs are charged per axis and independent, so each axis is solved on its own:
+// weighted median for linear cost, full scan of the range otherwise. None
+// without any crabs to align.
+pub fn align<const D: usize>(crabs: &[Crab<D>], cost_fn: &AxisCostFn) -> Option<MultiAlignment<D>> {
+    let weights = crabs.iter().map(|c| c.weight).collect::<Vec<_>>();
+    let mut position = [0; D];
+    for (axis, target) in position.iter_mut().enumerate() {
+        let values = crabs.iter().map(|c| c.position[axis]).collect::<Vec<_>>();
+        *target = match cost_fn {
+            AxisCostFn::Linear => weighted_median(&values, &weights)?,
+            _ => {
+                generic_min_distance_value(&values, &weights, |x, t| {
+                    cost_fn.axis_cost((x - t).abs() as i64)
+                })?
+                .0
+            }
+        };
+    }
+
+    let crab_costs = crabs
+        .iter()
+        .map(|c| {
+            let moves = (0..D)
+                .map(|axis| cost_fn.axis_cost((c.position[axis] - position[axis]).abs() as i64))
+                .fold(0i64, |acc, m| acc.saturating_add(m));
+            c.weight.saturating_mul(moves)
+        })
+        .collect::<Vec<_>>();
+
+    Some(MultiAlignment {
+        position: position,
+        cost: crab_costs.iter().fold(0, |acc, c| acc.saturating_add(*c)),
+        crab_costs: crab_costs,
+    })
+}
@@ -0,0 +1,124 @@
+use crate::util::BResult;
+
+// Minimal SVG writer. Shapes are drawn in user coordinates; `crop` picks
+// the visible part of that space and `zoom` scales it on output.
+pub struct Svg {
+    view: (f64, f64, f64, f64),
+    zoom: f64,
+    elements: Vec<String>,
+}
+
+impl Svg {
+    pub fn new(width: f64, height: f64) -> Svg {
+        Svg {
+            view: (0.0, 0.0, width, height),
+            zoom: 1.0,
+            elements: vec![],
+        }
+    }
+
+    pub fn crop(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.view = (x, y, width, height)
+    }
+
+    pub fn zoom(&mut self, factor: f64) {
+        self.zoom = factor
+    }
+
+    // reads `--zoom=F` and `--crop=X,Y,W,H` from the command line
+    pub fn apply_args(&mut self) {
+        if let Some(zoom) = crate::util::flag_value("zoom").and_then(|z| z.parse().ok()) {
+            self.zoom(zoom);
+        }
+        if let Some(crop) = crate::util::flag_value("crop") {
+            let parts = crop
+                .split(',')
+                .map(|p| p.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>();
+            match parts.as_deref() {
+                Ok([x, y, w, h]) => self.crop(*x, *y, *w, *h),
+                _ => println!("warning: ignoring malformed --crop={}", crop),
+            }
+        }
+    }
+
+    pub fn background(&mut self, fill: &str) {
+        let (x, y, w, h) = self.view;
+        self.rect(x, y, w, h, fill);
+    }
+
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, stroke: &str, width: f64) {
+        self.elements.push(format!(
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#,
+            num(x1), num(y1), num(x2), num(y2), stroke, num(width)
+        ));
+    }
+
+    pub fn circle(&mut self, cx: f64, cy: f64, r: f64, fill: &str) {
+        self.elements.push(format!(
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+            num(cx),
+            num(cy),
+            num(r),
+            fill
+        ));
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, fill: &str) {
+        self.elements.push(format!(
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+            num(x),
+            num(y),
+            num(width),
+            num(height),
+            fill
+        ));
+    }
+
+    pub fn text(&mut self, x: f64, y: f64, size: f64, text: &str) {
+        self.elements.push(format!(
+            r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+            num(x), num(y), num(size), escape(text)
+        ));
+    }
+
+    pub fn render(&self) -> String {
+        let (x, y, w, h) = self.view;
+        let mut out = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+            num(w * self.zoom),
+            num(h * self.zoom),
+            num(x),
+            num(y),
+            num(w),
+            num(h)
+        );
+        out.push('\n');
+        for e in self.elements.iter() {
+            out.push_str("  ");
+            out.push_str(e);
+            out.push('\n');
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    pub fn save(&self, path: &str) -> BResult<()> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(std::fs::write(path, self.render())?)
+    }
+}
+
+// two decimals are plenty, and whole numbers stay whole
+fn num(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -1,17 +1,29 @@
 use regex::Regex;
 use std::collections::HashMap;
+use std::io::BufRead;
 use std::str::FromStr;
 
 pub type BResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 pub fn read_file_maybe_test(path: &str, test_path: &str) -> BResult<String> {
-    if std::env::args().any(|a| a == "--test") {
+    if has_flag("test") {
         read_file(test_path)
     } else {
         read_file(path)
     }
 }
 
+pub fn has_flag(name: &str) -> bool {
+    let flag = format!("--{}", name);
+    std::env::args().any(|a| a == flag)
+}
+
+// value of a `--name=value` command line argument
+pub fn flag_value(name: &str) -> Option<String> {
+    let prefix = format!("--{}=", name);
+    std::env::args().find_map(|a| a.strip_prefix(&prefix).map(String::from))
+}
+
 pub fn read_file(path: &str) -> BResult<String> {
     Ok(std::fs::read_to_string(path)?)
 }
@@ -36,6 +48,81 @@ where
         .collect::<Result<Vec<T>, _>>()?)
 }
 
+// Opens a file for `stream_lines` instead of reading it all at once.
+pub fn open_file(path: &str) -> BResult<std::io::BufReader<std::fs::File>> {
+    Ok(std::io::BufReader::new(std::fs::File::open(path)?))
+}
+
+pub fn open_file_maybe_test(
+    path: &str,
+    test_path: &str,
+) -> BResult<std::io::BufReader<std::fs::File>> {
+    if has_flag("test") {
+        open_file(test_path)
+    } else {
+        open_file(path)
+    }
+}
+
+// The streaming counterpart of `parse_lines`: parses one line at a time, so
+// memory use doesn't grow with the input. Iteration stops at the first bad
+// line, so call `finish` before reporting anything worked out from the lines;
+// otherwise a bad line just looks like the end of the input.
+pub fn stream_lines<T: FromStr, R: BufRead>(reader: R) -> LineStream<T, R> {
+    LineStream {
+        lines: reader.lines(),
+        line: 0,
+        error: None,
+        parsed: std::marker::PhantomData,
+    }
+}
+
+// a `LineStream` over a file, as the streaming days hand it to their parts
+pub type FileLines<T> = LineStream<T, std::io::BufReader<std::fs::File>>;
+
+pub struct LineStream<T, R> {
+    lines: std::io::Lines<R>,
+    line: usize,
+    error: Option<Boom>,
+    parsed: std::marker::PhantomData<T>,
+}
+
+impl<T, R> LineStream<T, R> {
+    pub fn finish(&self) -> Result<(), Boom> {
+        match &self.error {
+            Some(e) => Err(Boom::from_display(&e.value)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T, R> Iterator for LineStream<T, R>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+    R: BufRead,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.error.is_some() {
+            return None;
+        }
+        self.line += 1;
+        let parsed = match self.lines.next()? {
+            Ok(text) => text.parse::<T>().map_err(Boom::from_display),
+            Err(e) => Err(Boom::from_display(e)),
+        };
+        match parsed {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error = Some(Boom::from_display(format!("line {}: {}", self.line, e)));
+                None
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Boom {
     pub value: String,
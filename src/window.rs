@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+// Sliding windows over any iterator with the window size picked at runtime.
+// Only the current window is buffered, so unbounded streams are fine.
+pub trait WindowOps: Iterator + Sized {
+    fn sliding(self, size: usize) -> Sliding<Self>
+    where
+        Self::Item: Clone,
+    {
+        assert!(size > 0, "window size must be positive");
+        Sliding {
+            iter: self,
+            size: size,
+            buffer: VecDeque::with_capacity(size),
+        }
+    }
+
+    fn rolling(self, size: usize) -> Rolling<Self>
+    where
+        Self: Iterator<Item = i64>,
+    {
+        assert!(size > 0, "window size must be positive");
+        Rolling {
+            iter: self,
+            size: size,
+            seen: 0,
+            values: VecDeque::with_capacity(size),
+            sum: 0,
+            mins: VecDeque::new(),
+            maxs: VecDeque::new(),
+        }
+    }
+
+    fn trend(self) -> Trend
+    where
+        Self::Item: PartialOrd,
+    {
+        let mut trend = Trend::default();
+        let mut previous = None;
+        for item in self {
+            if let Some(prev) = previous {
+                if item > prev {
+                    trend.increases += 1;
+                } else if item < prev {
+                    trend.decreases += 1;
+                } else {
+                    trend.unchanged += 1;
+                }
+            }
+            previous = Some(item);
+        }
+        trend
+    }
+}
+
+impl<I: Iterator> WindowOps for I {}
+
+pub struct Sliding<I: Iterator> {
+    iter: I,
+    size: usize,
+    buffer: VecDeque<I::Item>,
+}
+
+impl<I> Iterator for Sliding<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        if self.buffer.len() == self.size {
+            self.buffer.pop_front();
+        }
+        while self.buffer.len() < self.size {
+            self.buffer.push_back(self.iter.next()?);
+        }
+        Some(self.buffer.iter().cloned().collect())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowStats {
+    pub start: usize,
+    pub sum: i64,
+    pub mean: f64,
+    pub min: i64,
+    pub max: i64,
+}
+
+// Rolling sum, mean, min and max, each O(1) amortized per item: the sum is
+// kept running and min/max come from monotonic queues of (index, value).
+pub struct Rolling<I> {
+    iter: I,
+    size: usize,
+    seen: usize,
+    values: VecDeque<i64>,
+    sum: i64,
+    mins: VecDeque<(usize, i64)>,
+    maxs: VecDeque<(usize, i64)>,
+}
+
+impl<I: Iterator<Item = i64>> Iterator for Rolling<I> {
+    type Item = WindowStats;
+
+    fn next(&mut self) -> Option<WindowStats> {
+        loop {
+            let value = self.iter.next()?;
+            let index = self.seen;
+            self.seen += 1;
+
+            self.values.push_back(value);
+            self.sum += value;
+            if self.values.len() > self.size {
+                self.sum -= self.values.pop_front().unwrap();
+            }
+
+            while self.mins.back().is_some_and(|(_, v)| *v >= value) {
+                self.mins.pop_back();
+            }
+            self.mins.push_back((index, value));
+            while self.maxs.back().is_some_and(|(_, v)| *v <= value) {
+                self.maxs.pop_back();
+            }
+            self.maxs.push_back((index, value));
+
+            if self.values.len() < self.size {
+                continue;
+            }
+            let start = index + 1 - self.size;
+            while self.mins.front().is_some_and(|(i, _)| *i < start) {
+                self.mins.pop_front();
+            }
+            while self.maxs.front().is_some_and(|(i, _)| *i < start) {
+                self.maxs.pop_front();
+            }
+
+            return Some(WindowStats {
+                start: start,
+                sum: self.sum,
+                mean: self.sum as f64 / self.size as f64,
+                min: self.mins.front().unwrap().1,
+                max: self.maxs.front().unwrap().1,
+            });
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Trend {
+    pub increases: usize,
+    pub decreases: usize,
+    pub unchanged: usize,
+}